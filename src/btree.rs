@@ -0,0 +1,213 @@
+use std::collections::btree_map;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::RangeBounds;
+use std::sync::Arc;
+
+use super::store::{self, Ref};
+use super::{BiMap, Ordered, Storage};
+
+/// A `BiMap` that keeps both sides sorted, with a `BTreeMap` in each direction.
+///
+/// Create one with `BiBTreeMap::default()`: like `HashMap::new`, `BiMap::new` only exists for the
+/// default storage, so that the storage of `BiMap::new()` never needs to be spelled out.
+pub type BiBTreeMap<T, U> = BiMap<T, U, Ordered>;
+
+impl<T: Ord, U: Eq, RS: Storage<U, T>> BiMap<T, U, Ordered, RS> {
+    /// An iterator over the pairs whose left value lies in `range`, sorted by left value.
+    pub fn range_left<R: RangeBounds<T>>(&self, range: R) -> RangeLeft<'_, T, U> {
+        RangeLeft {
+            inner: store::range(&self.left_to_right, range),
+        }
+    }
+    /// The pair with the smallest left value.
+    pub fn first_left(&self) -> Option<(&T, &U)> {
+        self.left_to_right.first_key_value().map(|(l, r)| (&**l, &**r))
+    }
+    /// The pair with the largest left value.
+    pub fn last_left(&self) -> Option<(&T, &U)> {
+        self.left_to_right.last_key_value().map(|(l, r)| (&**l, &**r))
+    }
+}
+
+impl<T: Eq, U: Ord, LS: Storage<T, U>> BiMap<T, U, LS, Ordered> {
+    /// An iterator over the pairs whose right value lies in `range`, sorted by right value.
+    pub fn range_right<R: RangeBounds<U>>(&self, range: R) -> RangeRight<'_, T, U> {
+        RangeRight {
+            inner: store::range(&self.right_to_left, range),
+        }
+    }
+    /// An iterator over all pairs, sorted by right value.
+    pub fn iter_by_right(&self) -> RangeRight<'_, T, U> {
+        self.range_right(..)
+    }
+    /// The pair with the smallest right value.
+    pub fn first_right(&self) -> Option<(&T, &U)> {
+        self.right_to_left.first_key_value().map(|(r, l)| (&**l, &**r))
+    }
+    /// The pair with the largest right value.
+    pub fn last_right(&self) -> Option<(&T, &U)> {
+        self.right_to_left.last_key_value().map(|(r, l)| (&**l, &**r))
+    }
+}
+
+/// Iterator over the pairs in a range of left values, created by `BiMap::range_left`.
+pub struct RangeLeft<'a, T: 'a, U: 'a> {
+    inner: btree_map::Range<'a, Ref<T>, Arc<U>>,
+}
+
+impl<'a, T, U> Iterator for RangeLeft<'a, T, U> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next().map(|(l, r)| (&**l, &**r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U> DoubleEndedIterator for RangeLeft<'a, T, U> {
+    fn next_back(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next_back().map(|(l, r)| (&**l, &**r))
+    }
+}
+
+impl<'a, T, U> FusedIterator for RangeLeft<'a, T, U> {}
+
+impl<'a, T, U> Clone for RangeLeft<'a, T, U> {
+    fn clone(&self) -> RangeLeft<'a, T, U> {
+        RangeLeft {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for RangeLeft<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the pairs in a range of right values, created by `BiMap::range_right`.
+pub struct RangeRight<'a, T: 'a, U: 'a> {
+    inner: btree_map::Range<'a, Ref<U>, Arc<T>>,
+}
+
+impl<'a, T, U> Iterator for RangeRight<'a, T, U> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next().map(|(r, l)| (&**l, &**r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U> DoubleEndedIterator for RangeRight<'a, T, U> {
+    fn next_back(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next_back().map(|(r, l)| (&**l, &**r))
+    }
+}
+
+impl<'a, T, U> FusedIterator for RangeRight<'a, T, U> {}
+
+impl<'a, T, U> Clone for RangeRight<'a, T, U> {
+    fn clone(&self) -> RangeRight<'a, T, U> {
+        RangeRight {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for RangeRight<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BiMap, Hashed, Ordered};
+    use super::BiBTreeMap;
+
+    #[test]
+    fn api() {
+        let mut map: BiBTreeMap<&str, &str> = BiBTreeMap::default();
+        map.insert_key("abc", "xyz");
+        map.insert_value("123", "def");
+
+        assert_eq!(map.get_key(&"def"), Some(&"123"));
+        assert_eq!(map.update_key("abc", "456"), Some("xyz"));
+        assert_eq!(map.get_value(&"456"), Some(&"abc"));
+        assert!(map.try_insert("ghi", "123").is_err());
+        assert_eq!(map.remove(&"def"), Some("123"));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.clone(), map);
+    }
+
+    #[test]
+    fn sorted() {
+        let map: BiBTreeMap<u32, char> =
+            vec![(3, 'a'), (1, 'c'), (4, 'b'), (2, 'd')].into_iter().collect();
+
+        assert_eq!(map.left_values().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(map.right_values().cloned().collect::<Vec<_>>(), vec!['a', 'b', 'c', 'd']);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(&1, &'c'), (&2, &'d'), (&3, &'a'), (&4, &'b')]
+        );
+        assert_eq!(
+            map.iter_by_right().collect::<Vec<_>>(),
+            vec![(&3, &'a'), (&4, &'b'), (&1, &'c'), (&2, &'d')]
+        );
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            vec![(1, 'c'), (2, 'd'), (3, 'a'), (4, 'b')]
+        );
+    }
+
+    #[test]
+    fn range() {
+        let map: BiBTreeMap<u32, char> =
+            vec![(3, 'a'), (1, 'c'), (4, 'b'), (2, 'd')].into_iter().collect();
+
+        assert_eq!(map.range_left(2..4).collect::<Vec<_>>(), vec![(&2, &'d'), (&3, &'a')]);
+        assert_eq!(map.range_left(..=1).rev().collect::<Vec<_>>(), vec![(&1, &'c')]);
+        assert_eq!(
+            map.range_right('b'..).collect::<Vec<_>>(),
+            vec![(&4, &'b'), (&1, &'c'), (&2, &'d')]
+        );
+        assert_eq!(map.range_right('e'..).next(), None);
+    }
+
+    #[test]
+    fn first_last() {
+        let map: BiBTreeMap<u32, char> =
+            vec![(3, 'a'), (1, 'c'), (4, 'b'), (2, 'd')].into_iter().collect();
+
+        assert_eq!(map.first_left(), Some((&1, &'c')));
+        assert_eq!(map.last_left(), Some((&4, &'b')));
+        assert_eq!(map.first_right(), Some((&3, &'a')));
+        assert_eq!(map.last_right(), Some((&2, &'d')));
+        assert_eq!(BiBTreeMap::<u32, char>::default().first_left(), None);
+    }
+
+    #[test]
+    fn mixed() {
+        let mut map: BiMap<&str, u32, Hashed, Ordered> = BiMap::default();
+        map.insert_key("abc", 30);
+        map.insert_key("def", 10);
+        map.insert_key("ghi", 20);
+
+        assert_eq!(map.get_key(&"def"), Some(&10));
+        assert_eq!(map.range_right(15..).collect::<Vec<_>>(), vec![(&"ghi", &20), (&"abc", &30)]);
+        assert_eq!(map.first_right(), Some((&"def", &10)));
+        assert_eq!(map.right_values().cloned().collect::<Vec<_>>(), vec![10, 20, 30]);
+
+        let map: BiMap<u32, &str, Ordered, Hashed> = map.into_iter().map(|(l, r)| (r, l)).collect();
+        assert_eq!(map.range_left(..20).collect::<Vec<_>>(), vec![(&10, &"def")]);
+        assert_eq!(map.last_left(), Some((&30, &"abc")));
+    }
+}
@@ -0,0 +1,334 @@
+use std::error::Error;
+use std::fmt;
+use std::iter::FusedIterator;
+
+use super::{BiMap, Hashed, Iter, Lookup, Storage};
+
+/// What `BiMap::compose` does with pairs that have no match in the other map.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Unmatched {
+    /// Leave them out of the result.
+    Drop,
+    /// Fail, handing them back in a `ComposeError`.
+    Report,
+}
+
+type Composition<T, U, V, LS, RS> = Result<BiMap<T, V, LS, RS>, ComposeError<T, U, V, LS, RS>>;
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// Chains this map with `other`: `l` is bound to `r` in the result if it is bound to some `m`
+    /// here and `m` is bound to `r` in `other`.
+    ///
+    /// The composition of two one-to-one maps is one-to-one, so this only fails if `unmatched` is
+    /// `Report` and some pairs of either map have no match in the other.
+    pub fn compose<V: Eq>(
+        self,
+        mut other: BiMap<U, V, LS, RS>,
+        unmatched: Unmatched,
+    ) -> Composition<T, U, V, LS, RS>
+    where
+        LS: Storage<U, V> + Storage<T, V>,
+        RS: Storage<V, U> + Storage<V, T>,
+        <LS as Storage<T, V>>::Map: Default,
+        <RS as Storage<V, T>>::Map: Default,
+    {
+        let mut map = BiMap::default();
+        let mut left = self;
+        let pairs: Vec<_> = left.drain().collect();
+        for (l, m) in pairs {
+            match other.remove(&m) {
+                Some(r) => map.insert_key(l, r),
+                None => left.insert_key(l, m),
+            }
+        }
+        if unmatched == Unmatched::Drop || (left.is_empty() && other.is_empty()) {
+            Ok(map)
+        } else {
+            Err(ComposeError {
+                map,
+                left,
+                right: other,
+            })
+        }
+    }
+    /// A lazy composition of this map with `other`, answering the same queries as `compose`
+    /// would without building a map.
+    pub fn composed<'a, V: Eq>(
+        &'a self,
+        other: &'a BiMap<U, V, LS, RS>,
+    ) -> Composed<'a, T, U, V, LS, RS>
+    where
+        LS: Storage<U, V>,
+        RS: Storage<V, U>,
+    {
+        Composed {
+            first: self,
+            second: other,
+        }
+    }
+}
+
+/// The result of `BiMap::compose` with `Unmatched::Report`, when some pairs had no match.
+pub struct ComposeError<T, U, V, LS = Hashed, RS = LS>
+where
+    T: Eq,
+    U: Eq,
+    V: Eq,
+    LS: Storage<T, U> + Storage<U, V> + Storage<T, V>,
+    RS: Storage<U, T> + Storage<V, U> + Storage<V, T>,
+{
+    /// The composition of the pairs that did match.
+    pub map: BiMap<T, V, LS, RS>,
+    /// The pairs of the first map whose right value is not in the second.
+    pub left: BiMap<T, U, LS, RS>,
+    /// The pairs of the second map whose left value is not in the first.
+    pub right: BiMap<U, V, LS, RS>,
+}
+
+impl<T, U, V, LS, RS> fmt::Display for ComposeError<T, U, V, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    V: Eq,
+    LS: Storage<T, U> + Storage<U, V> + Storage<T, V>,
+    RS: Storage<U, T> + Storage<V, U> + Storage<V, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} pairs of the first map and {} of the second had no match",
+            self.left.len(),
+            self.right.len()
+        )
+    }
+}
+
+impl<T, U, V, LS, RS> fmt::Debug for ComposeError<T, U, V, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    V: Eq + fmt::Debug,
+    LS: Storage<T, U> + Storage<U, V> + Storage<T, V>,
+    RS: Storage<U, T> + Storage<V, U> + Storage<V, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ComposeError")
+            .field("map", &self.map)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .finish()
+    }
+}
+
+impl<T, U, V, LS, RS> Error for ComposeError<T, U, V, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    V: Eq + fmt::Debug,
+    LS: Storage<T, U> + Storage<U, V> + Storage<T, V>,
+    RS: Storage<U, T> + Storage<V, U> + Storage<V, T>,
+{
+}
+
+/// Two `BiMap`s chained together, created by `BiMap::composed`.
+///
+/// Lookups go through both maps, so they cost about twice as much as on a single map.
+pub struct Composed<'a, T: 'a, U: 'a, V: 'a, LS = Hashed, RS = LS>
+where
+    LS: Storage<T, U> + Storage<U, V> + 'a,
+    RS: Storage<U, T> + Storage<V, U> + 'a,
+{
+    first: &'a BiMap<T, U, LS, RS>,
+    second: &'a BiMap<U, V, LS, RS>,
+}
+
+impl<'a, T, U, V, LS, RS> Composed<'a, T, U, V, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    V: Eq,
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+    pub fn get_key<Q: ?Sized>(&self, l: &Q) -> Option<&'a V>
+    where
+        <LS as Storage<T, U>>::Map: Lookup<T, U, Q>,
+    {
+        let second = self.second;
+        self.first.get_key(l).and_then(|m| second.get_key(m))
+    }
+    pub fn get_value<Q: ?Sized>(&self, r: &Q) -> Option<&'a T>
+    where
+        <RS as Storage<V, U>>::Map: Lookup<V, U, Q>,
+    {
+        let first = self.first;
+        self.second.get_value(r).and_then(|m| first.get_value(m))
+    }
+    pub fn contains_left<Q: ?Sized>(&self, l: &Q) -> bool
+    where
+        <LS as Storage<T, U>>::Map: Lookup<T, U, Q>,
+    {
+        self.get_key(l).is_some()
+    }
+    pub fn contains_right<Q: ?Sized>(&self, r: &Q) -> bool
+    where
+        <RS as Storage<V, U>>::Map: Lookup<V, U, Q>,
+    {
+        self.get_value(r).is_some()
+    }
+    /// An iterator over the composed pairs, in the order of the first map.
+    pub fn iter(&self) -> ComposedIter<'a, T, U, V, LS, RS> {
+        ComposedIter {
+            inner: self.first.iter(),
+            second: self.second,
+        }
+    }
+}
+
+impl<'a, T, U, V, LS, RS> Clone for Composed<'a, T, U, V, LS, RS>
+where
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+    fn clone(&self) -> Composed<'a, T, U, V, LS, RS> {
+        *self
+    }
+}
+
+impl<'a, T, U, V, LS, RS> Copy for Composed<'a, T, U, V, LS, RS>
+where
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+}
+
+impl<'a, T, U, V, LS, RS> fmt::Debug for Composed<'a, T, U, V, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq,
+    V: Eq + fmt::Debug,
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the pairs of a `Composed` map, created by `Composed::iter`.
+pub struct ComposedIter<'a, T: 'a, U: 'a, V: 'a, LS = Hashed, RS = LS>
+where
+    LS: Storage<T, U> + Storage<U, V> + 'a,
+    RS: Storage<U, T> + Storage<V, U> + 'a,
+{
+    inner: Iter<'a, T, U, LS>,
+    second: &'a BiMap<U, V, LS, RS>,
+}
+
+impl<'a, T, U, V, LS, RS> Iterator for ComposedIter<'a, T, U, V, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    V: Eq,
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+    type Item = (&'a T, &'a V);
+
+    fn next(&mut self) -> Option<(&'a T, &'a V)> {
+        let second = self.second;
+        self.inner.find_map(|(l, m)| second.get_key(m).map(|r| (l, r)))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, T, U, V, LS, RS> FusedIterator for ComposedIter<'a, T, U, V, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    V: Eq,
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+}
+
+impl<'a, T, U, V, LS, RS> Clone for ComposedIter<'a, T, U, V, LS, RS>
+where
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+    fn clone(&self) -> ComposedIter<'a, T, U, V, LS, RS> {
+        ComposedIter {
+            inner: self.inner.clone(),
+            second: self.second,
+        }
+    }
+}
+
+impl<'a, T, U, V, LS, RS> fmt::Debug for ComposedIter<'a, T, U, V, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq,
+    V: Eq + fmt::Debug,
+    LS: Storage<T, U> + Storage<U, V>,
+    RS: Storage<U, T> + Storage<V, U>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::BiBTreeMap;
+    use super::Unmatched;
+
+    #[test]
+    fn compose() {
+        let first: BiBTreeMap<&str, u32> =
+            vec![("abc", 1), ("def", 2), ("ghi", 3)].into_iter().collect();
+        let second: BiBTreeMap<u32, char> =
+            vec![(1, 'x'), (3, 'z'), (4, 'w')].into_iter().collect();
+
+        let map = first.clone().compose(second.clone(), Unmatched::Drop).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"abc", &'x'), (&"ghi", &'z')]);
+
+        let error = first.compose(second, Unmatched::Report).unwrap_err();
+        assert_eq!(error.map, map);
+        assert_eq!(error.left.iter().collect::<Vec<_>>(), vec![(&"def", &2)]);
+        assert_eq!(error.right.iter().collect::<Vec<_>>(), vec![(&4, &'w')]);
+        assert_eq!(
+            error.to_string(),
+            "1 pairs of the first map and 1 of the second had no match"
+        );
+    }
+
+    #[test]
+    fn compose_complete() {
+        let first: BiBTreeMap<u32, u32> = vec![(1, 10), (2, 20)].into_iter().collect();
+        let second: BiBTreeMap<u32, u32> = vec![(20, 200), (10, 100)].into_iter().collect();
+
+        let map = first.compose(second, Unmatched::Report).unwrap();
+        assert_eq!(map.get_key(&1), Some(&100));
+        assert_eq!(map.get_value(&200), Some(&2));
+    }
+
+    #[test]
+    fn composed() {
+        let first: BiBTreeMap<&str, u32> =
+            vec![("abc", 1), ("def", 2), ("ghi", 3)].into_iter().collect();
+        let second: BiBTreeMap<u32, char> =
+            vec![(1, 'x'), (3, 'z'), (4, 'w')].into_iter().collect();
+        let composed = first.composed(&second);
+
+        assert_eq!(composed.get_key(&"abc"), Some(&'x'));
+        assert_eq!(composed.get_key(&"def"), None);
+        assert_eq!(composed.get_value(&'z'), Some(&"ghi"));
+        assert!(!composed.contains_right(&'w'));
+        assert!(composed.contains_left(&"ghi"));
+        assert_eq!(composed.iter().collect::<Vec<_>>(), vec![(&"abc", &'x'), (&"ghi", &'z')]);
+        assert_eq!(format!("{:?}", composed), r#"{"abc": 'x', "ghi": 'z'}"#);
+    }
+}
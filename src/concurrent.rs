@@ -0,0 +1,452 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::store::{Query, Ref};
+use super::{Conflict, Side};
+
+/// The number of shards used by `ConcurrentBiMap::new`.
+const SHARDS: usize = 16;
+
+/// A one-to-one mapping like `BiMap` that can be shared between threads.
+///
+/// The pairs are spread over a number of shards, each behind its own lock, by the hash of their
+/// values. Lookups only take the read lock of one shard. An operation on a pair locks every shard
+/// it touches in both directions before changing anything, so other threads never see one half of
+/// a pair without the other.
+///
+/// Elements are handed out as `Arc`s, since references into the map cannot outlive its locks.
+pub struct ConcurrentBiMap<T, U, S = RandomState> {
+    shards: Box<[RwLock<Shard<T, U>>]>,
+    hasher: S,
+}
+
+/// The pairs whose left values, and the pairs whose right values, hash to one shard.
+struct Shard<T, U> {
+    left_to_right: Half<T, U>,
+    right_to_left: Half<U, T>,
+}
+
+type Half<K, V> = HashMap<Ref<K>, Arc<V>>;
+
+/// The previous partner of an updated value, or the reason the update was rejected.
+type Updated<V, P> = Result<Option<Arc<V>>, SharedUpdateError<V, P>>;
+
+impl<T: Eq + Hash, U: Eq + Hash> ConcurrentBiMap<T, U> {
+    pub fn new() -> ConcurrentBiMap<T, U> {
+        ConcurrentBiMap::with_shards(SHARDS)
+    }
+    /// An empty map spread over `shards` locks; more shards means less contention between
+    /// writers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> ConcurrentBiMap<T, U> {
+        ConcurrentBiMap::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash, S: BuildHasher> ConcurrentBiMap<T, U, S> {
+    /// Like `with_shards`, picking the shard of each value with `hasher`.
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> ConcurrentBiMap<T, U, S> {
+        assert!(shards > 0, "a ConcurrentBiMap needs at least one shard");
+        let shards = (0..shards)
+            .map(|_| {
+                RwLock::new(Shard {
+                    left_to_right: HashMap::new(),
+                    right_to_left: HashMap::new(),
+                })
+            })
+            .collect();
+        ConcurrentBiMap { shards, hasher }
+    }
+    pub fn get_key<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> Option<Arc<U>>
+    where
+        T: Borrow<Q>,
+    {
+        self.peek::<LeftToRight, Q>(l)
+    }
+    pub fn get_value<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> Option<Arc<T>>
+    where
+        U: Borrow<Q>,
+    {
+        self.peek::<RightToLeft, Q>(r)
+    }
+    pub fn contains_left<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.get_key(l).is_some()
+    }
+    pub fn contains_right<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> bool
+    where
+        U: Borrow<Q>,
+    {
+        self.get_value(r).is_some()
+    }
+    pub fn insert_key(&self, l: T, r: U) {
+        if let Err(error) = self.try_insert(l, r) {
+            panic!("{}", error);
+        }
+    }
+    /// Inserts the pair `(l, r)`, unless either value is already bound to a different partner.
+    ///
+    /// Inserting a pair that is already present succeeds without changing the map.
+    pub fn try_insert(&self, l: T, r: U) -> Result<(), Conflict<T, U>> {
+        let (i, j) = (self.index(&l), self.index(&r));
+        let mut locked = self.lock(&[i, j]);
+        let left = locked.shard(i).left_to_right.get(Query::new(&l)).map(|old| **old == r);
+        let right = locked.shard(j).right_to_left.contains_key(Query::new(&r));
+        let side = match (left, right) {
+            (Some(true), _) => return Ok(()),
+            (None, false) => {
+                let (l, r) = (Arc::new(l), Arc::new(r));
+                locked.shard(i).left_to_right.insert(Ref(Arc::clone(&l)), Arc::clone(&r));
+                locked.shard(j).right_to_left.insert(Ref(r), l);
+                return Ok(());
+            }
+            (Some(_), false) => Side::Left,
+            (None, true) => Side::Right,
+            (Some(_), true) => Side::Both,
+        };
+        Err(Conflict { pair: (l, r), side })
+    }
+    /// Binds a left value to `r`, returning its previous partner, unless `r` is bound to a
+    /// different left value.
+    ///
+    /// When `l` is not yet in the map, the pair `(l, r)` is inserted and `None` is returned.
+    pub fn try_update_key(&self, l: T, r: U) -> Updated<U, T> {
+        self.update::<LeftToRight>(l, r)
+    }
+    /// Binds a right value to `l`, returning its previous partner, unless `l` is bound to a
+    /// different right value.
+    ///
+    /// When `r` is not yet in the map, the pair `(l, r)` is inserted and `None` is returned.
+    pub fn try_update_value(&self, r: U, l: T) -> Updated<T, U> {
+        self.update::<RightToLeft>(r, l)
+    }
+    /// Removes the pair holding a left value, returning its partner.
+    pub fn remove<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> Option<Arc<U>>
+    where
+        T: Borrow<Q>,
+    {
+        self.remove_by::<LeftToRight, Q>(l)
+    }
+    /// Removes the pair holding a right value, returning its partner.
+    pub fn remove_value<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> Option<Arc<T>>
+    where
+        U: Borrow<Q>,
+    {
+        self.remove_by::<RightToLeft, Q>(r)
+    }
+    /// The number of pairs, counted while holding every shard.
+    pub fn len(&self) -> usize {
+        let shards: Vec<_> = (0..self.shards.len()).map(|i| self.read(i)).collect();
+        shards.iter().map(|shard| shard.left_to_right.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn clear(&self) {
+        let all: Vec<_> = (0..self.shards.len()).collect();
+        let mut locked = self.lock(&all);
+        for i in all {
+            let shard = locked.shard(i);
+            shard.left_to_right.clear();
+            shard.right_to_left.clear();
+        }
+    }
+    /// All pairs, as they were at one moment while holding every shard.
+    pub fn snapshot(&self) -> Vec<(Arc<T>, Arc<U>)> {
+        let shards: Vec<_> = (0..self.shards.len()).map(|i| self.read(i)).collect();
+        shards
+            .iter()
+            .flat_map(|shard| shard.left_to_right.iter())
+            .map(|(l, r)| (Arc::clone(&l.0), Arc::clone(r)))
+            .collect()
+    }
+    fn index<Q: ?Sized + Hash>(&self, value: &Q) -> usize {
+        (self.hasher.hash_one(value) % self.shards.len() as u64) as usize
+    }
+    fn read(&self, i: usize) -> RwLockReadGuard<'_, Shard<T, U>> {
+        self.shards[i].read().expect("a thread panicked while changing the map")
+    }
+    /// Write-locks the shards at `indices`, always in the same order so that two operations
+    /// cannot each wait for a shard the other holds.
+    fn lock(&self, indices: &[usize]) -> Locked<'_, T, U> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let guards = indices
+            .into_iter()
+            .map(|i| (i, self.shards[i].write().expect("a thread panicked while changing the map")))
+            .collect();
+        Locked { guards }
+    }
+    fn peek<D: Direction<T, U>, Q: ?Sized + Eq + Hash>(&self, key: &Q) -> Option<Arc<D::To>>
+    where
+        D::From: Borrow<Q>,
+    {
+        D::half(&self.read(self.index(key))).get(Query::new(key)).cloned()
+    }
+    fn remove_by<D: Direction<T, U>, Q: ?Sized + Eq + Hash>(&self, key: &Q) -> Option<Arc<D::To>>
+    where
+        D::From: Borrow<Q>,
+    {
+        let i = self.index(key);
+        loop {
+            let partner = self.peek::<D, Q>(key)?;
+            let j = self.index(&*partner);
+            let mut locked = self.lock(&[i, j]);
+            if !bound_to::<T, U, D, Q>(locked.shard(i), key, &partner) {
+                continue;
+            }
+            D::half_mut(locked.shard(i)).remove(Query::new(key));
+            D::Back::half_mut(locked.shard(j)).remove(Query::new(&*partner));
+            return Some(partner);
+        }
+    }
+    fn update<D: Direction<T, U>>(&self, key: D::From, value: D::To) -> Updated<D::To, D::From> {
+        let (i, j) = (self.index(&key), self.index(&value));
+        loop {
+            let old = self.peek::<D, D::From>(&key);
+            let k = old.as_ref().map_or(i, |old| self.index(&**old));
+            let mut locked = self.lock(&[i, j, k]);
+            let unchanged = match &old {
+                Some(old) => bound_to::<T, U, D, D::From>(locked.shard(i), &key, old),
+                None => !D::half(locked.shard(i)).contains_key(Query::new(&key)),
+            };
+            if !unchanged {
+                continue;
+            }
+            let back = D::Back::half(locked.shard(j));
+            if let Some(partner) = back.get(Query::new(&value)) {
+                if **partner != key {
+                    return Err(SharedUpdateError {
+                        partner: Arc::clone(partner),
+                        value,
+                    });
+                }
+            }
+            let key = match &old {
+                Some(old) => {
+                    let half = D::half_mut(locked.shard(i));
+                    let (key, _) = half.remove_entry(Query::new(&key)).unwrap();
+                    D::Back::half_mut(locked.shard(k)).remove(Query::new(&**old));
+                    key.0
+                }
+                None => Arc::new(key),
+            };
+            let value = Arc::new(value);
+            D::half_mut(locked.shard(i)).insert(Ref(Arc::clone(&key)), Arc::clone(&value));
+            D::Back::half_mut(locked.shard(j)).insert(Ref(value), key);
+            return Ok(old);
+        }
+    }
+}
+
+/// Whether `key` is still bound to the very `partner` seen before its shards were locked.
+fn bound_to<T, U, D: Direction<T, U>, Q: ?Sized + Eq + Hash>(
+    shard: &Shard<T, U>,
+    key: &Q,
+    partner: &Arc<D::To>,
+) -> bool
+where
+    D::From: Borrow<Q>,
+{
+    D::half(shard).get(Query::new(key)).is_some_and(|current| Arc::ptr_eq(current, partner))
+}
+
+/// The write locks of the shards taken part in one operation.
+struct Locked<'a, T, U> {
+    guards: Vec<(usize, RwLockWriteGuard<'a, Shard<T, U>>)>,
+}
+
+impl<'a, T, U> Locked<'a, T, U> {
+    fn shard(&mut self, i: usize) -> &mut Shard<T, U> {
+        let (_, guard) = self.guards.iter_mut().find(|(j, _)| *j == i).expect("shard is locked");
+        guard
+    }
+}
+
+/// One direction of the pairs in a shard, so that operations can be written once for both.
+trait Direction<T, U> {
+    type From: Eq + Hash;
+    type To: Eq + Hash;
+    type Back: Direction<T, U, From = Self::To, To = Self::From>;
+
+    fn half(shard: &Shard<T, U>) -> &Half<Self::From, Self::To>;
+    fn half_mut(shard: &mut Shard<T, U>) -> &mut Half<Self::From, Self::To>;
+}
+
+struct LeftToRight;
+
+struct RightToLeft;
+
+impl<T: Eq + Hash, U: Eq + Hash> Direction<T, U> for LeftToRight {
+    type From = T;
+    type To = U;
+    type Back = RightToLeft;
+
+    fn half(shard: &Shard<T, U>) -> &Half<T, U> {
+        &shard.left_to_right
+    }
+    fn half_mut(shard: &mut Shard<T, U>) -> &mut Half<T, U> {
+        &mut shard.left_to_right
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> Direction<T, U> for RightToLeft {
+    type From = U;
+    type To = T;
+    type Back = LeftToRight;
+
+    fn half(shard: &Shard<T, U>) -> &Half<U, T> {
+        &shard.right_to_left
+    }
+    fn half_mut(shard: &mut Shard<T, U>) -> &mut Half<U, T> {
+        &mut shard.right_to_left
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> Default for ConcurrentBiMap<T, U> {
+    fn default() -> ConcurrentBiMap<T, U> {
+        ConcurrentBiMap::new()
+    }
+}
+
+impl<T, U, S> fmt::Debug for ConcurrentBiMap<T, U, S>
+where
+    T: Eq + Hash + fmt::Debug,
+    U: Eq + Hash + fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.snapshot()).finish()
+    }
+}
+
+/// The reason a `ConcurrentBiMap` rejected an update: the new `value` is already bound to
+/// `partner`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SharedUpdateError<V, P> {
+    pub value: V,
+    pub partner: Arc<P>,
+}
+
+impl<V, P> fmt::Display for SharedUpdateError<V, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("new partner is already bound to a different value")
+    }
+}
+
+impl<V: fmt::Debug, P: fmt::Debug> Error for SharedUpdateError<V, P> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::super::{Conflict, Side};
+    use super::{ConcurrentBiMap, SharedUpdateError};
+
+    /// Checks that every pair can be found from both of its sides.
+    fn assert_consistent(map: &ConcurrentBiMap<u32, u32>) {
+        let pairs = map.snapshot();
+        assert_eq!(pairs.len(), map.len());
+        for (l, r) in pairs {
+            assert_eq!(map.get_key(&*l), Some(Arc::clone(&r)));
+            assert_eq!(map.get_value(&*r), Some(l));
+        }
+    }
+
+    #[test]
+    fn single_thread() {
+        let map = ConcurrentBiMap::with_shards(3);
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+
+        assert_eq!(map.get_key("abc"), Some(Arc::new(1)));
+        assert_eq!(map.get_value(&2).as_deref().map(String::as_str), Some("def"));
+        assert_eq!(
+            map.try_insert("abc".to_string(), 2),
+            Err(Conflict { pair: ("abc".to_string(), 2), side: Side::Both })
+        );
+        assert_eq!(map.try_update_key("abc".to_string(), 3), Ok(Some(Arc::new(1))));
+        assert_eq!(
+            map.try_update_value(3, "def".to_string()),
+            Err(SharedUpdateError { value: "def".to_string(), partner: Arc::new(2) })
+        );
+        assert_eq!(map.remove_value(&3).as_deref().map(String::as_str), Some("abc"));
+        assert!(!map.contains_left("abc"));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn update_absent() {
+        let map = ConcurrentBiMap::new();
+        map.insert_key(1, 10);
+
+        assert_eq!(map.try_update_key(2, 20), Ok(None));
+        assert_eq!(map.get_key(&2), Some(Arc::new(20)));
+        assert_eq!(map.try_update_value(30, 3), Ok(None));
+        assert_eq!(map.get_value(&30), Some(Arc::new(3)));
+        assert_eq!(
+            map.try_update_key(4, 10),
+            Err(SharedUpdateError { value: 10, partner: Arc::new(1) })
+        );
+        assert!(!map.contains_left(&4));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn one_winner() {
+        let map = &ConcurrentBiMap::new();
+        let winners: usize = thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|l| scope.spawn(move || map.try_insert(l, 0).is_ok() as usize))
+                .collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).sum()
+        });
+
+        assert_eq!(winners, 1);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn contended() {
+        let map = ConcurrentBiMap::with_shards(4);
+        for l in 0..32 {
+            map.insert_key(l, l);
+        }
+        thread::scope(|scope| {
+            for t in 0..4 {
+                let map = &map;
+                scope.spawn(move || {
+                    for n in 0..500 {
+                        let l = (n * 7 + t) % 32;
+                        let _ = map.try_update_key(l, 32 + (n * 13 + t * 5) % 64);
+                        if n % 50 == 0 {
+                            map.remove(&((l + 1) % 32));
+                            let _ = map.try_insert((l + 1) % 32, 100 + t * 1000 + n);
+                        }
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..200 {
+                    let pairs = map.snapshot();
+                    let rights: HashSet<_> = pairs.iter().map(|(_, r)| Arc::clone(r)).collect();
+                    assert_eq!(rights.len(), pairs.len());
+                }
+            });
+        });
+        assert_consistent(&map);
+    }
+}
@@ -0,0 +1,227 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{BiMap, Side, Storage};
+
+/// One difference between two versions of a `BiMap`, as computed by `BiMap::diff`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change<T, U> {
+    /// The pair is only in the new version.
+    Added(T, U),
+    /// The pair is only in the old version.
+    Removed(T, U),
+    /// The left value is bound to `to` instead of `from`.
+    LeftRebound { left: T, from: U, to: U },
+    /// The right value is bound to `to` instead of `from`.
+    RightRebound { right: U, from: T, to: T },
+}
+
+impl<T, U> Change<T, U> {
+    /// The pair this change expects to find, if any.
+    pub fn before(&self) -> Option<(&T, &U)> {
+        match self {
+            Change::Added(..) => None,
+            Change::Removed(l, r) => Some((l, r)),
+            Change::LeftRebound { left, from, .. } => Some((left, from)),
+            Change::RightRebound { right, from, .. } => Some((from, right)),
+        }
+    }
+    /// The pair this change leaves behind, if any.
+    pub fn after(&self) -> Option<(&T, &U)> {
+        match self {
+            Change::Added(l, r) => Some((l, r)),
+            Change::Removed(..) => None,
+            Change::LeftRebound { left, to, .. } => Some((left, to)),
+            Change::RightRebound { right, to, .. } => Some((to, right)),
+        }
+    }
+}
+
+impl<T: Eq + Clone, U: Eq + Clone, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// The changes that turn `self` into `new`.
+    ///
+    /// A pair that was replaced by one sharing its left value is reported as `LeftRebound`, one
+    /// replaced by a pair sharing its right value as `RightRebound`; where both apply, the left
+    /// value wins and the other pair is reported as removed.
+    pub fn diff(&self, new: &BiMap<T, U, LS, RS>) -> Vec<Change<T, U>> {
+        let mut changes = Vec::new();
+        for (l, r) in self.iter() {
+            match (new.get_key(l), new.get_value(r)) {
+                (Some(to), _) if to == r => {}
+                (Some(to), _) => changes.push(Change::LeftRebound {
+                    left: l.clone(),
+                    from: r.clone(),
+                    to: to.clone(),
+                }),
+                (None, Some(to)) if !self.contains_left(to) => changes.push(Change::RightRebound {
+                    right: r.clone(),
+                    from: l.clone(),
+                    to: to.clone(),
+                }),
+                (None, _) => changes.push(Change::Removed(l.clone(), r.clone())),
+            }
+        }
+        for (l, r) in new.iter() {
+            let rebound = match self.get_value(r) {
+                Some(from) => from == l || !new.contains_left(from),
+                None => false,
+            };
+            if !self.contains_left(l) && !rebound {
+                changes.push(Change::Added(l.clone(), r.clone()));
+            }
+        }
+        changes
+    }
+    /// Applies `changes`, as computed by `diff`, to this map.
+    ///
+    /// Fails if any change expects a pair that is not in the map, or would bind a value that is
+    /// still bound to a different partner. Either way, the map is left unchanged.
+    pub fn apply<'a>(&mut self, changes: &'a [Change<T, U>]) -> Result<(), PatchError<'a, T, U>> {
+        let stale: Vec<_> = changes
+            .iter()
+            .filter(|change| change.before().is_some_and(|(l, r)| self.get_key(l) != Some(r)))
+            .collect();
+        if !stale.is_empty() {
+            return Err(PatchError::Stale(stale));
+        }
+        for (l, _) in changes.iter().filter_map(Change::before) {
+            self.remove(l);
+        }
+        let mut inserted = Vec::new();
+        for change in changes {
+            let (l, r) = match change.after() {
+                Some((l, r)) if self.get_key(l) != Some(r) => (l, r),
+                _ => continue,
+            };
+            if let Err(error) = self.try_insert(l.clone(), r.clone()) {
+                let side = error.side();
+                for (l, _) in inserted {
+                    self.remove(l);
+                }
+                for (l, r) in changes.iter().filter_map(Change::before) {
+                    self.insert_key(l.clone(), r.clone());
+                }
+                return Err(PatchError::Clash { change, side });
+            }
+            inserted.push((l, r));
+        }
+        Ok(())
+    }
+}
+
+/// The reason `BiMap::apply` rejected a list of changes.
+#[derive(Debug, Eq, PartialEq)]
+pub enum PatchError<'a, T: 'a, U: 'a> {
+    /// These changes expect pairs that are not in the map.
+    Stale(Vec<&'a Change<T, U>>),
+    /// This change would bind a value that is already bound to a different partner.
+    Clash { change: &'a Change<T, U>, side: Side },
+}
+
+impl<'a, T, U> fmt::Display for PatchError<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::Stale(changes) => {
+                write!(f, "{} changes expect pairs that are not in the map", changes.len())
+            }
+            PatchError::Clash { side, .. } => write!(f, "change clashes with the map: {}", side),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> Error for PatchError<'a, T, U> {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BiBTreeMap, Side};
+    use super::{Change, PatchError};
+
+    #[test]
+    fn diff() {
+        let old: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect();
+        let new: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'x'), (5, 'c'), (6, 'y')].into_iter().collect();
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                Change::LeftRebound { left: 2, from: 'b', to: 'x' },
+                Change::RightRebound { right: 'c', from: 3, to: 5 },
+                Change::Removed(4, 'd'),
+                Change::Added(6, 'y'),
+            ]
+        );
+        assert_eq!(old.diff(&old), vec![]);
+        assert_eq!(new.diff(&BiBTreeMap::default()).len(), 4);
+    }
+
+    #[test]
+    fn diff_overlap() {
+        let old: BiBTreeMap<u32, char> = vec![(1, 'a'), (2, 'b')].into_iter().collect();
+        let new: BiBTreeMap<u32, char> = vec![(1, 'b'), (2, 'a')].into_iter().collect();
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![
+                Change::LeftRebound { left: 1, from: 'a', to: 'b' },
+                Change::LeftRebound { left: 2, from: 'b', to: 'a' },
+            ]
+        );
+        let mut map = old.clone();
+        assert_eq!(map.apply(&changes), Ok(()));
+        assert_eq!(map, new);
+    }
+
+    #[test]
+    fn apply() {
+        let old: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect();
+        let new: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'x'), (5, 'c'), (6, 'y')].into_iter().collect();
+        let changes = old.diff(&new);
+
+        let mut map = old.clone();
+        assert_eq!(map.apply(&changes), Ok(()));
+        assert_eq!(map, new);
+
+        map.insert_key(7, 'z');
+        assert_eq!(new.diff(&map), vec![Change::Added(7, 'z')]);
+    }
+
+    #[test]
+    fn apply_stale() {
+        let old: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect();
+        let new: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'x'), (5, 'c'), (6, 'y')].into_iter().collect();
+        let changes = old.diff(&new);
+        let mut map = old.clone();
+        map.remove(&4);
+        map.update_key(2, 'e');
+
+        let error = map.apply(&changes).unwrap_err();
+        assert_eq!(error, PatchError::Stale(vec![&changes[0], &changes[2]]));
+        assert_eq!(error.to_string(), "2 changes expect pairs that are not in the map");
+        assert_eq!(map.get_key(&2), Some(&'e'));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn apply_clash() {
+        let old: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect();
+        let new: BiBTreeMap<u32, char> =
+            vec![(1, 'a'), (2, 'x'), (5, 'c'), (6, 'y')].into_iter().collect();
+        let changes = old.diff(&new);
+        let mut map = old.clone();
+        map.insert_key(7, 'y');
+
+        assert_eq!(
+            map.apply(&changes),
+            Err(PatchError::Clash { change: &changes[3], side: Side::Right })
+        );
+        assert_eq!(map.diff(&old), vec![Change::Removed(7, 'y')]);
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::sync::Arc;
+
+use super::{get, rebind, remove_entry, BiMap, Hashed, Lookup, Storage, Store, UpdateError};
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// A view of the pair holding the left value `l`, or of the place where it would go.
+    pub fn entry_left(&mut self, l: T) -> Entry<'_, T, U, LS, RS> {
+        Entry::new(&mut self.left_to_right, &mut self.right_to_left, l)
+    }
+    /// A view of the pair holding the right value `r`, or of the place where it would go.
+    ///
+    /// The entry is keyed by `r`, so its partners are left values.
+    pub fn entry_right(&mut self, r: U) -> Entry<'_, U, T, RS, LS> {
+        Entry::new(&mut self.right_to_left, &mut self.left_to_right, r)
+    }
+}
+
+/// A view of a single key of a `BiMap`, created by `BiMap::entry_left` or `BiMap::entry_right`.
+///
+/// `K` is the side the entry was looked up by and `V` the side of its partner, with `KS` and `VS`
+/// the storages of the two directions.
+pub enum Entry<'a, K: 'a, V: 'a, KS: Storage<K, V> + 'a = Hashed, VS = KS>
+where
+    VS: Storage<V, K> + 'a,
+{
+    Occupied(OccupiedEntry<'a, K, V, KS, VS>),
+    Vacant(VacantEntry<'a, K, V, KS, VS>),
+}
+
+impl<'a, K: Eq, V: Eq, KS: Storage<K, V>, VS: Storage<V, K>> Entry<'a, K, V, KS, VS> {
+    pub(crate) fn new(
+        map1: &'a mut KS::Map,
+        map2: &'a mut VS::Map,
+        key: K,
+    ) -> Entry<'a, K, V, KS, VS> {
+        if map1.contains_key(&key) {
+            Entry::Occupied(OccupiedEntry { key, map1, map2 })
+        } else {
+            Entry::Vacant(VacantEntry { key, map1, map2 })
+        }
+    }
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K, V, KS, VS> fmt::Debug for Entry<'a, K, V, KS, VS>
+where
+    K: Eq + fmt::Debug,
+    V: Eq + fmt::Debug,
+    KS: Storage<K, V>,
+    VS: Storage<V, K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Occupied").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Vacant").field(entry).finish(),
+        }
+    }
+}
+
+/// A key that is bound to a partner.
+pub struct OccupiedEntry<'a, K: 'a, V: 'a, KS: Storage<K, V> + 'a = Hashed, VS = KS>
+where
+    VS: Storage<V, K> + 'a,
+{
+    key: K,
+    map1: &'a mut KS::Map,
+    map2: &'a mut VS::Map,
+}
+
+impl<'a, K: Eq, V: Eq, KS: Storage<K, V>, VS: Storage<V, K>> OccupiedEntry<'a, K, V, KS, VS> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    pub fn partner(&self) -> &V {
+        get(&*self.map1, &self.key).expect("occupied entry has a partner")
+    }
+    pub fn into_partner(self) -> &'a V {
+        get(self.map1, &self.key).expect("occupied entry has a partner")
+    }
+    /// Binds the key to `partner` instead, returning the previous partner.
+    ///
+    /// Nothing changes if `partner` is already bound to a different key.
+    pub fn replace_partner(&mut self, partner: V) -> Result<V, UpdateError<'_, V, K>> {
+        rebind(&mut *self.map1, &mut *self.map2, &self.key, partner).map(Option::unwrap)
+    }
+    /// Removes the pair from the map, returning the partner.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+    /// Removes the pair from the map, returning the stored key along with its partner.
+    pub fn remove_entry(self) -> (K, V) {
+        remove_entry(self.map1, self.map2, &self.key).expect("occupied entry has a partner")
+    }
+}
+
+impl<'a, K, V, KS, VS> fmt::Debug for OccupiedEntry<'a, K, V, KS, VS>
+where
+    K: Eq + fmt::Debug,
+    V: Eq + fmt::Debug,
+    KS: Storage<K, V>,
+    VS: Storage<V, K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", self.key())
+            .field("partner", self.partner())
+            .finish()
+    }
+}
+
+/// A key that is not in the map yet.
+pub struct VacantEntry<'a, K: 'a, V: 'a, KS: Storage<K, V> + 'a = Hashed, VS = KS>
+where
+    VS: Storage<V, K> + 'a,
+{
+    key: K,
+    map1: &'a mut KS::Map,
+    map2: &'a mut VS::Map,
+}
+
+impl<'a, K: Eq, V: Eq, KS: Storage<K, V>, VS: Storage<V, K>> VacantEntry<'a, K, V, KS, VS> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    pub fn into_key(self) -> K {
+        self.key
+    }
+    /// Binds the key to `partner`, returning a reference to it, unless `partner` is already bound
+    /// to a different key.
+    pub fn insert(self, partner: V) -> Result<&'a V, UpdateError<'a, V, K>> {
+        if self.map2.contains_key(&partner) {
+            return Err(UpdateError {
+                partner: get(self.map2, &partner).unwrap(),
+                value: partner,
+            });
+        }
+        let (key, partner) = (Arc::new(self.key), Arc::new(partner));
+        self.map1.insert(Arc::clone(&key), Arc::clone(&partner));
+        self.map2.insert(partner, Arc::clone(&key));
+        Ok(get(self.map1, &*key).unwrap())
+    }
+}
+
+impl<'a, K, V, KS, VS> fmt::Debug for VacantEntry<'a, K, V, KS, VS>
+where
+    K: Eq + fmt::Debug,
+    V: Eq,
+    KS: Storage<K, V>,
+    VS: Storage<V, K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(self.key()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BiBTreeMap, BiMap, UpdateError};
+    use super::Entry;
+
+    #[test]
+    fn occupied() {
+        let mut map: BiMap<&str, &str> = vec![("abc", "xyz"), ("def", "123")].into_iter().collect();
+        match map.entry_left("abc") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!((entry.key(), entry.partner()), (&"abc", &"xyz"));
+                assert_eq!(
+                    entry.replace_partner("123"),
+                    Err(UpdateError { value: "123", partner: &"def" })
+                );
+                assert_eq!(entry.replace_partner("456"), Ok("xyz"));
+                assert_eq!(entry.into_partner(), &"456");
+            }
+            Entry::Vacant(_) => panic!("abc is in the map"),
+        }
+        assert_eq!(map.get_value(&"456"), Some(&"abc"));
+        assert_eq!(map.get_value(&"xyz"), None);
+
+        match map.entry_right("123") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), ("123", "def")),
+            Entry::Vacant(_) => panic!("123 is in the map"),
+        }
+        assert_eq!(map.get_key(&"def"), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn vacant() {
+        let mut map: BiMap<&str, &str> = vec![("abc", "xyz"), ("def", "123")].into_iter().collect();
+        match map.entry_left("ghi") {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &"ghi");
+                assert_eq!(
+                    entry.insert("xyz"),
+                    Err(UpdateError { value: "xyz", partner: &"abc" })
+                );
+            }
+            Entry::Occupied(_) => panic!("ghi is not in the map"),
+        }
+        assert_eq!(map.len(), 2);
+
+        match map.entry_right("789") {
+            Entry::Vacant(entry) => assert_eq!(entry.insert("ghi"), Ok(&"ghi")),
+            Entry::Occupied(_) => panic!("789 is not in the map"),
+        }
+        assert_eq!(map.get_key(&"ghi"), Some(&"789"));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn get_or_insert() {
+        let mut map: BiBTreeMap<String, u32> = BiBTreeMap::default();
+        for word in ["b", "a", "b", "c", "a"] {
+            let next = map.len() as u32;
+            match map.entry_left(word.to_string()) {
+                Entry::Occupied(entry) => assert!(*entry.partner() < next),
+                Entry::Vacant(entry) => assert_eq!(entry.insert(next), Ok(&next)),
+            }
+        }
+        assert_eq!(
+            map.iter_by_right().map(|(l, _)| &l[..]).collect::<Vec<_>>(),
+            vec!["b", "a", "c"]
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! A persistent hash array mapped trie, the structure behind `PersistentBiMap`.
+//!
+//! Every node is immutable and shared through an `Arc`. Changing an entry copies the nodes on the
+//! path to it and shares all others with the original, so the old trie stays valid. Hashes are
+//! computed by the caller and consumed 5 bits per level, lowest bits first.
+
+use std::borrow::Borrow;
+use std::slice;
+use std::sync::Arc;
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+pub(crate) struct Hamt<K, V> {
+    root: Arc<Node<K, V>>,
+    len: usize,
+}
+
+/// The slots of one level, holding only the positions marked in `bitmap`.
+struct Node<K, V> {
+    bitmap: u32,
+    slots: Vec<Slot<K, V>>,
+}
+
+/// A trie or node without an entry, along with that entry.
+type Removed<N, K, V> = (N, (Arc<K>, Arc<V>));
+
+enum Slot<K, V> {
+    Leaf(u64, Arc<K>, Arc<V>),
+    /// Entries whose keys differ but whose hashes are all the same.
+    Collision(u64, Arc<Vec<(Arc<K>, Arc<V>)>>),
+    Branch(Arc<Node<K, V>>),
+}
+
+impl<K: Eq, V> Hamt<K, V> {
+    pub(crate) fn new() -> Hamt<K, V> {
+        Hamt {
+            root: Arc::new(Node {
+                bitmap: 0,
+                slots: Vec::new(),
+            }),
+            len: 0,
+        }
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+    pub(crate) fn get<Q: ?Sized + Eq>(&self, hash: u64, key: &Q) -> Option<&Arc<V>>
+    where
+        K: Borrow<Q>,
+    {
+        get(&self.root, hash, 0, key)
+    }
+    /// A copy with `key` bound to `value`, along with the value it replaces.
+    ///
+    /// A key that is already in the trie keeps its original `Arc`.
+    pub(crate) fn insert(
+        &self,
+        hash: u64,
+        key: Arc<K>,
+        value: Arc<V>,
+    ) -> (Hamt<K, V>, Option<Arc<V>>) {
+        let (root, old) = insert(&self.root, hash, 0, key, value);
+        let len = if old.is_some() { self.len } else { self.len + 1 };
+        (
+            Hamt {
+                root: Arc::new(root),
+                len,
+            },
+            old,
+        )
+    }
+    /// A copy without `key`, along with the entry that held it, or `None` if it is not in the trie.
+    pub(crate) fn remove<Q: ?Sized + Eq>(
+        &self,
+        hash: u64,
+        key: &Q,
+    ) -> Option<Removed<Hamt<K, V>, K, V>>
+    where
+        K: Borrow<Q>,
+    {
+        let (root, entry) = remove(&self.root, hash, 0, key)?;
+        let hamt = Hamt {
+            root: Arc::new(root),
+            len: self.len - 1,
+        };
+        Some((hamt, entry))
+    }
+    pub(crate) fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: vec![self.root.slots.iter()],
+            collision: [].iter(),
+            len: self.len,
+        }
+    }
+}
+
+impl<K, V> Clone for Hamt<K, V> {
+    fn clone(&self) -> Hamt<K, V> {
+        Hamt {
+            root: Arc::clone(&self.root),
+            len: self.len,
+        }
+    }
+}
+
+impl<K, V> Clone for Slot<K, V> {
+    fn clone(&self) -> Slot<K, V> {
+        match self {
+            Slot::Leaf(hash, key, value) => Slot::Leaf(*hash, Arc::clone(key), Arc::clone(value)),
+            Slot::Collision(hash, entries) => Slot::Collision(*hash, Arc::clone(entries)),
+            Slot::Branch(node) => Slot::Branch(Arc::clone(node)),
+        }
+    }
+}
+
+impl<K, V> Slot<K, V> {
+    /// The hash of the entries in a slot that is not a branch.
+    fn hash(&self) -> u64 {
+        match self {
+            Slot::Leaf(hash, ..) | Slot::Collision(hash, _) => *hash,
+            Slot::Branch(_) => unreachable!("branches hold entries with different hashes"),
+        }
+    }
+}
+
+/// The bit of `node.bitmap` for `hash` at this level, and the index of its slot.
+fn position<K, V>(node: &Node<K, V>, hash: u64, shift: u32) -> (u32, usize) {
+    let bit = 1 << ((hash >> shift) & MASK);
+    (bit, (node.bitmap & (bit - 1)).count_ones() as usize)
+}
+
+fn get<'a, K, V, Q>(node: &'a Node<K, V>, hash: u64, shift: u32, key: &Q) -> Option<&'a Arc<V>>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Eq,
+{
+    let (bit, index) = position(node, hash, shift);
+    if node.bitmap & bit == 0 {
+        return None;
+    }
+    match &node.slots[index] {
+        Slot::Leaf(h, k, v) => Some(v).filter(|_| *h == hash && (**k).borrow() == key),
+        Slot::Collision(h, entries) if *h == hash => {
+            entries.iter().find(|(k, _)| (**k).borrow() == key).map(|(_, v)| v)
+        }
+        Slot::Collision(..) => None,
+        Slot::Branch(child) => get(child, hash, shift + BITS, key),
+    }
+}
+
+fn insert<K: Eq, V>(
+    node: &Node<K, V>,
+    hash: u64,
+    shift: u32,
+    key: Arc<K>,
+    value: Arc<V>,
+) -> (Node<K, V>, Option<Arc<V>>) {
+    let (bit, index) = position(node, hash, shift);
+    let mut slots = node.slots.clone();
+    if node.bitmap & bit == 0 {
+        slots.insert(index, Slot::Leaf(hash, key, value));
+        let node = Node {
+            bitmap: node.bitmap | bit,
+            slots,
+        };
+        return (node, None);
+    }
+    let (slot, old) = match &node.slots[index] {
+        Slot::Leaf(h, k, v) if *h == hash && *k == key => {
+            (Slot::Leaf(hash, Arc::clone(k), value), Some(Arc::clone(v)))
+        }
+        Slot::Leaf(h, k, v) if *h == hash => {
+            let entries = vec![(Arc::clone(k), Arc::clone(v)), (key, value)];
+            (Slot::Collision(hash, Arc::new(entries)), None)
+        }
+        Slot::Collision(h, entries) if *h == hash => {
+            let mut entries = (**entries).clone();
+            let old = match entries.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, v)) => Some(std::mem::replace(v, value)),
+                None => {
+                    entries.push((key, value));
+                    None
+                }
+            };
+            (Slot::Collision(hash, Arc::new(entries)), old)
+        }
+        Slot::Branch(child) => {
+            let (child, old) = insert(child, hash, shift + BITS, key, value);
+            (Slot::Branch(Arc::new(child)), old)
+        }
+        other => {
+            let branch = split(shift + BITS, other.clone(), Slot::Leaf(hash, key, value));
+            (Slot::Branch(Arc::new(branch)), None)
+        }
+    };
+    slots[index] = slot;
+    let node = Node {
+        bitmap: node.bitmap,
+        slots,
+    };
+    (node, old)
+}
+
+/// A node holding two slots that share a position up to `shift` but have different hashes.
+fn split<K, V>(shift: u32, a: Slot<K, V>, b: Slot<K, V>) -> Node<K, V> {
+    let (i, j) = ((a.hash() >> shift) & MASK, (b.hash() >> shift) & MASK);
+    if i == j {
+        return Node {
+            bitmap: 1 << i,
+            slots: vec![Slot::Branch(Arc::new(split(shift + BITS, a, b)))],
+        };
+    }
+    let slots = if i < j { vec![a, b] } else { vec![b, a] };
+    Node {
+        bitmap: 1 << i | 1 << j,
+        slots,
+    }
+}
+
+fn remove<K, V, Q>(
+    node: &Node<K, V>,
+    hash: u64,
+    shift: u32,
+    key: &Q,
+) -> Option<Removed<Node<K, V>, K, V>>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Eq,
+{
+    let (bit, index) = position(node, hash, shift);
+    if node.bitmap & bit == 0 {
+        return None;
+    }
+    let (slot, entry) = match &node.slots[index] {
+        Slot::Leaf(h, k, v) if *h == hash && (**k).borrow() == key => {
+            (None, (Arc::clone(k), Arc::clone(v)))
+        }
+        Slot::Leaf(..) => return None,
+        Slot::Collision(h, entries) if *h == hash => {
+            let found = entries.iter().position(|(k, _)| (**k).borrow() == key)?;
+            let mut entries = (**entries).clone();
+            let entry = entries.remove(found);
+            let slot = if entries.len() == 1 {
+                let (k, v) = entries.pop().unwrap();
+                Slot::Leaf(hash, k, v)
+            } else {
+                Slot::Collision(hash, Arc::new(entries))
+            };
+            (Some(slot), entry)
+        }
+        Slot::Collision(..) => return None,
+        Slot::Branch(child) => {
+            let (child, entry) = remove(child, hash, shift + BITS, key)?;
+            // Keeps the trie as shallow as if the entry had never been there.
+            let slot = match &child.slots[..] {
+                [] => None,
+                [only] if !matches!(only, Slot::Branch(_)) => Some(only.clone()),
+                _ => Some(Slot::Branch(Arc::new(child))),
+            };
+            (slot, entry)
+        }
+    };
+    let mut slots = node.slots.clone();
+    let mut bitmap = node.bitmap;
+    match slot {
+        Some(slot) => slots[index] = slot,
+        None => {
+            slots.remove(index);
+            bitmap &= !bit;
+        }
+    }
+    Some((Node { bitmap, slots }, entry))
+}
+
+/// Iterator over the entries of a `Hamt`, depth first.
+pub(crate) struct Iter<'a, K: 'a, V: 'a> {
+    stack: Vec<slice::Iter<'a, Slot<K, V>>>,
+    collision: slice::Iter<'a, (Arc<K>, Arc<V>)>,
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a Arc<K>, &'a Arc<V>);
+
+    fn next(&mut self) -> Option<(&'a Arc<K>, &'a Arc<V>)> {
+        loop {
+            if let Some((key, value)) = self.collision.next() {
+                self.len -= 1;
+                return Some((key, value));
+            }
+            match self.stack.last_mut()?.next() {
+                None => {
+                    self.stack.pop();
+                }
+                Some(Slot::Leaf(_, key, value)) => {
+                    self.len -= 1;
+                    return Some((key, value));
+                }
+                Some(Slot::Collision(_, entries)) => self.collision = entries.iter(),
+                Some(Slot::Branch(node)) => self.stack.push(node.slots.iter()),
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> Clone for Iter<'a, K, V> {
+    fn clone(&self) -> Iter<'a, K, V> {
+        Iter {
+            stack: self.stack.clone(),
+            collision: self.collision.clone(),
+            len: self.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Hamt;
+
+    /// Inserts `key` under a hash chosen by the test, to force shared prefixes and collisions.
+    fn with(hamt: &Hamt<u32, u32>, hash: u64, key: u32) -> Hamt<u32, u32> {
+        hamt.insert(hash, Arc::new(key), Arc::new(key * 10)).0
+    }
+
+    fn keys(hamt: &Hamt<u32, u32>) -> Vec<u32> {
+        let mut keys: Vec<_> = hamt.iter().map(|(key, _)| **key).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn collisions() {
+        let empty = Hamt::new();
+        // 1 and 2 share their lowest 20 bits, 3 collides with 2.
+        let hamt = with(&with(&with(&empty, 0x5_00000, 1), 0x6_00000, 2), 0x6_00000, 3);
+
+        assert_eq!(hamt.len(), 3);
+        assert_eq!(keys(&hamt), vec![1, 2, 3]);
+        assert_eq!(hamt.get(0x6_00000, &3).map(|v| **v), Some(30));
+        assert_eq!(hamt.get(0x6_00000, &1), None);
+        assert_eq!(empty.len(), 0);
+
+        let (removed, (key, _)) = hamt.remove(0x6_00000, &2).unwrap();
+        assert_eq!((*key, removed.len()), (2, 2));
+        assert_eq!(removed.get(0x6_00000, &3).map(|v| **v), Some(30));
+        assert!(removed.remove(0x6_00000, &2).is_none());
+        assert_eq!(keys(&hamt), vec![1, 2, 3]);
+
+        let (removed, _) = removed.remove(0x5_00000, &1).unwrap();
+        assert_eq!(keys(&removed), vec![3]);
+    }
+
+    #[test]
+    fn many() {
+        let mut hamt = Hamt::new();
+        for key in 0..2000 {
+            hamt = with(&hamt, u64::from(key).wrapping_mul(0x9e37_79b9_7f4a_7c15), key);
+        }
+        let (replaced, old) = hamt.insert(0, Arc::new(0), Arc::new(1));
+        assert_eq!((old.map(|v| *v), replaced.len()), (Some(0), 2000));
+
+        for key in (0..2000).filter(|key| key % 3 == 0) {
+            let hash = u64::from(key).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            hamt = hamt.remove(hash, &key).unwrap().0;
+        }
+        assert_eq!(hamt.len(), 1333);
+        assert_eq!(hamt.iter().count(), 1333);
+        assert!(keys(&hamt).iter().all(|key| key % 3 != 0));
+    }
+}
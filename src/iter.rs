@@ -0,0 +1,471 @@
+use std::error::Error;
+use std::fmt;
+use std::iter::{FromIterator, FusedIterator};
+
+use super::{retain, unwrap, BiMap, Conflict, Hashed, Storage, Store};
+
+type Map<T, U, S> = <S as Storage<T, U>>::Map;
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// An iterator over all pairs, sorted by left value if the left side is ordered.
+    pub fn iter(&self) -> Iter<'_, T, U, LS> {
+        Iter {
+            inner: self.left_to_right.iter(),
+        }
+    }
+    /// An iterator over all left values, sorted if the left side is ordered.
+    pub fn left_values(&self) -> LeftValues<'_, T, U, LS> {
+        LeftValues {
+            inner: self.left_to_right.keys(),
+        }
+    }
+    /// An iterator over all right values, sorted if the right side is ordered.
+    pub fn right_values(&self) -> RightValues<'_, T, U, RS> {
+        RightValues {
+            inner: self.right_to_left.keys(),
+        }
+    }
+    /// Removes all pairs from the map, returning them as an iterator.
+    ///
+    /// Pairs that are not consumed are dropped along with the iterator.
+    pub fn drain(&mut self) -> Drain<'_, T, U, LS> {
+        self.right_to_left.clear();
+        Drain {
+            inner: self.left_to_right.drain(),
+        }
+    }
+    /// Keeps only the pairs for which `f` returns `true`.
+    pub fn retain<F: FnMut(&T, &U) -> bool>(&mut self, f: F) {
+        retain(&mut self.left_to_right, &mut self.right_to_left, f);
+    }
+    /// Builds a map from `iter`, keeping the first pair for every value and reporting every later
+    /// pair that clashes with it.
+    pub fn try_from_iter<I: IntoIterator<Item = (T, U)>>(
+        iter: I,
+    ) -> Result<Self, FromIterError<T, U, LS, RS>>
+    where
+        LS::Map: Default,
+        RS::Map: Default,
+    {
+        let mut map = BiMap::default();
+        match map.try_extend(iter) {
+            Ok(()) => Ok(map),
+            Err(conflicts) => Err(FromIterError { map, conflicts }),
+        }
+    }
+    /// Inserts every pair from `iter` that does not clash with the map, returning the ones that do.
+    pub fn try_extend<I: IntoIterator<Item = (T, U)>>(
+        &mut self,
+        iter: I,
+    ) -> Result<(), Vec<Conflict<T, U>>> {
+        let conflicts: Vec<_> = iter
+            .into_iter()
+            .filter_map(|(l, r)| self.try_insert(l, r).err().map(Conflict::from))
+            .collect();
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(conflicts)
+        }
+    }
+}
+
+/// Collects pairs with `insert_overwrite`: a later pair replaces all earlier pairs it clashes with.
+impl<T, U, LS, RS> FromIterator<(T, U)> for BiMap<T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    LS::Map: Default,
+    RS::Map: Default,
+{
+    fn from_iter<I: IntoIterator<Item = (T, U)>>(iter: I) -> BiMap<T, U, LS, RS> {
+        let mut map = BiMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Inserts pairs with `insert_overwrite`: a later pair replaces all earlier pairs it clashes with.
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> Extend<(T, U)> for BiMap<T, U, LS, RS> {
+    fn extend<I: IntoIterator<Item = (T, U)>>(&mut self, iter: I) {
+        for (l, r) in iter {
+            self.insert_overwrite(l, r);
+        }
+    }
+}
+
+/// The pairs rejected by `BiMap::try_from_iter`, `BiMap::try_union` or
+/// `BiMap::try_symmetric_difference`, along with the map built from the others.
+pub struct FromIterError<T, U, LS = Hashed, RS = LS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    pub map: BiMap<T, U, LS, RS>,
+    pub conflicts: Vec<Conflict<T, U>>,
+}
+
+impl<T, U, LS, RS> fmt::Display for FromIterError<T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} pairs clashed with pairs inserted before them", self.conflicts.len())
+    }
+}
+
+impl<T, U, LS, RS> fmt::Debug for FromIterError<T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FromIterError")
+            .field("map", &self.map)
+            .field("conflicts", &self.conflicts)
+            .finish()
+    }
+}
+
+impl<T, U, LS, RS> Error for FromIterError<T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+}
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> IntoIterator for BiMap<T, U, LS, RS> {
+    type Item = (T, U);
+    type IntoIter = IntoIter<T, U, LS>;
+
+    fn into_iter(self) -> IntoIter<T, U, LS> {
+        let BiMap {
+            left_to_right,
+            right_to_left,
+        } = self;
+        drop(right_to_left);
+        IntoIter {
+            inner: left_to_right.into_iter(),
+        }
+    }
+}
+
+impl<'a, T, U, LS, RS> IntoIterator for &'a BiMap<T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    type Item = (&'a T, &'a U);
+    type IntoIter = Iter<'a, T, U, LS>;
+
+    fn into_iter(self) -> Iter<'a, T, U, LS> {
+        self.iter()
+    }
+}
+
+/// Borrowing iterator over the pairs of a `BiMap`, created by `BiMap::iter`.
+pub struct Iter<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed> {
+    pub(crate) inner: <Map<T, U, LS> as Store<T, U>>::Iter<'a>,
+}
+
+impl<'a, T, U, LS: Storage<T, U>> Iterator for Iter<'a, T, U, LS> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next().map(|(l, r)| (&**l, &**r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U, LS: Storage<T, U>> ExactSizeIterator for Iter<'a, T, U, LS> {}
+impl<'a, T, U, LS: Storage<T, U>> FusedIterator for Iter<'a, T, U, LS> {}
+
+impl<'a, T, U, LS: Storage<T, U>> Clone for Iter<'a, T, U, LS> {
+    fn clone(&self) -> Iter<'a, T, U, LS> {
+        Iter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug, LS: Storage<T, U>> fmt::Debug for Iter<'a, T, U, LS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Borrowing iterator over the left values of a `BiMap`, created by `BiMap::left_values`.
+pub struct LeftValues<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed> {
+    pub(crate) inner: <Map<T, U, LS> as Store<T, U>>::Keys<'a>,
+}
+
+impl<'a, T, U, LS: Storage<T, U>> Iterator for LeftValues<'a, T, U, LS> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next().map(|l| &**l)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U, LS: Storage<T, U>> ExactSizeIterator for LeftValues<'a, T, U, LS> {}
+impl<'a, T, U, LS: Storage<T, U>> FusedIterator for LeftValues<'a, T, U, LS> {}
+
+impl<'a, T, U, LS: Storage<T, U>> Clone for LeftValues<'a, T, U, LS> {
+    fn clone(&self) -> LeftValues<'a, T, U, LS> {
+        LeftValues {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U, LS: Storage<T, U>> fmt::Debug for LeftValues<'a, T, U, LS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Borrowing iterator over the right values of a `BiMap`, created by `BiMap::right_values`.
+pub struct RightValues<'a, T: 'a, U: 'a, RS: Storage<U, T> + 'a = Hashed> {
+    pub(crate) inner: <Map<U, T, RS> as Store<U, T>>::Keys<'a>,
+}
+
+impl<'a, T, U, RS: Storage<U, T>> Iterator for RightValues<'a, T, U, RS> {
+    type Item = &'a U;
+
+    fn next(&mut self) -> Option<&'a U> {
+        self.inner.next().map(|r| &**r)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U, RS: Storage<U, T>> ExactSizeIterator for RightValues<'a, T, U, RS> {}
+impl<'a, T, U, RS: Storage<U, T>> FusedIterator for RightValues<'a, T, U, RS> {}
+
+impl<'a, T, U, RS: Storage<U, T>> Clone for RightValues<'a, T, U, RS> {
+    fn clone(&self) -> RightValues<'a, T, U, RS> {
+        RightValues {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T, U: fmt::Debug, RS: Storage<U, T>> fmt::Debug for RightValues<'a, T, U, RS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Owning iterator over the pairs of a `BiMap`.
+pub struct IntoIter<T, U, LS: Storage<T, U> = Hashed> {
+    inner: <Map<T, U, LS> as Store<T, U>>::IntoIter,
+}
+
+impl<T, U, LS: Storage<T, U>> Iterator for IntoIter<T, U, LS> {
+    type Item = (T, U);
+
+    fn next(&mut self) -> Option<(T, U)> {
+        self.inner.next().map(|(l, r)| (unwrap(l), unwrap(r)))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, U, LS: Storage<T, U>> ExactSizeIterator for IntoIter<T, U, LS> {}
+impl<T, U, LS: Storage<T, U>> FusedIterator for IntoIter<T, U, LS> {}
+
+impl<T, U, LS: Storage<T, U>> fmt::Debug for IntoIter<T, U, LS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IntoIter").field("remaining", &self.len()).finish()
+    }
+}
+
+/// Draining iterator over the pairs of a `BiMap`, created by `BiMap::drain`.
+pub struct Drain<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed> {
+    inner: <Map<T, U, LS> as Store<T, U>>::Drain<'a>,
+}
+
+impl<'a, T, U, LS: Storage<T, U>> Iterator for Drain<'a, T, U, LS> {
+    type Item = (T, U);
+
+    fn next(&mut self) -> Option<(T, U)> {
+        self.inner.next().map(|(l, r)| (unwrap(l), unwrap(r)))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U, LS: Storage<T, U>> ExactSizeIterator for Drain<'a, T, U, LS> {}
+impl<'a, T, U, LS: Storage<T, U>> FusedIterator for Drain<'a, T, U, LS> {}
+
+impl<'a, T, U, LS: Storage<T, U>> fmt::Debug for Drain<'a, T, U, LS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Drain").field("remaining", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BiMap, Conflict, Side};
+
+    #[test]
+    fn iter() {
+        let mut map = BiMap::new();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+        map.insert_key("ghi".to_string(), 3);
+        let mut pairs: Vec<_> = map.iter().collect();
+        pairs.sort();
+
+        assert_eq!(map.iter().len(), 3);
+        assert_eq!(
+            pairs,
+            vec![(&"abc".to_string(), &1), (&"def".to_string(), &2), (&"ghi".to_string(), &3)]
+        );
+        assert_eq!((&map).into_iter().count(), 3);
+    }
+
+    #[test]
+    fn values() {
+        let mut map = BiMap::new();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+        map.insert_key("ghi".to_string(), 3);
+        let mut lefts: Vec<_> = map.left_values().cloned().collect();
+        lefts.sort();
+        let mut rights: Vec<_> = map.right_values().cloned().collect();
+        rights.sort();
+
+        assert_eq!(map.left_values().len(), 3);
+        assert_eq!(map.right_values().len(), 3);
+        assert_eq!(lefts, vec!["abc", "def", "ghi"]);
+        assert_eq!(rights, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut map = BiMap::new();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+        map.insert_key("ghi".to_string(), 3);
+        let iter = map.into_iter();
+        assert_eq!(iter.len(), 3);
+        let mut pairs: Vec<_> = iter.collect();
+        pairs.sort();
+
+        assert_eq!(
+            pairs,
+            vec![("abc".to_string(), 1), ("def".to_string(), 2), ("ghi".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn drain() {
+        let mut map = BiMap::new();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+        map.insert_key("ghi".to_string(), 3);
+        let mut drain = map.drain();
+        assert_eq!(drain.len(), 3);
+        drain.next();
+        assert_eq!(drain.len(), 2);
+        drop(drain);
+
+        assert!(map.is_empty());
+        assert_eq!(map.get_value(&1), None);
+        map.insert_key("abc".to_string(), 1);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn retain() {
+        let mut map = BiMap::new();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+        map.insert_key("ghi".to_string(), 3);
+        map.retain(|_, r| r % 2 == 1);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_key(&"def".to_string()), None);
+        assert_eq!(map.get_value(&2), None);
+        assert_eq!(map.get_value(&3), Some(&"ghi".to_string()));
+        assert_eq!(map.left_to_right.len(), map.right_to_left.len());
+    }
+
+    #[test]
+    fn from_iter() {
+        let map: BiMap<&str, u32> = vec![("abc", 1), ("def", 2), ("abc", 3), ("ghi", 2)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_key(&"abc"), Some(&3));
+        assert_eq!(map.get_key(&"ghi"), Some(&2));
+        assert_eq!(map.get_key(&"def"), None);
+    }
+
+    #[test]
+    fn extend() {
+        let mut map = BiMap::new();
+        map.insert_key("abc", 1);
+        map.extend(vec![("def", 1), ("abc", 2)]);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_value(&1), Some(&"def"));
+        assert_eq!(map.get_value(&2), Some(&"abc"));
+    }
+
+    #[test]
+    fn try_from_iter() {
+        let map: BiMap<_, _> =
+            BiMap::try_from_iter(vec![("abc", 1), ("def", 2), ("abc", 1)]).unwrap();
+        assert_eq!(map.len(), 2);
+
+        let pairs = vec![("abc", 1), ("def", 2), ("abc", 3), ("ghi", 2), ("def", 1)];
+        let error = BiMap::<_, _>::try_from_iter(pairs).unwrap_err();
+        assert_eq!(error.map.len(), 2);
+        assert_eq!(error.map.get_key(&"abc"), Some(&1));
+        assert_eq!(
+            error.conflicts,
+            vec![
+                Conflict { pair: ("abc", 3), side: Side::Left },
+                Conflict { pair: ("ghi", 2), side: Side::Right },
+                Conflict { pair: ("def", 1), side: Side::Both },
+            ]
+        );
+    }
+
+    #[test]
+    fn try_extend() {
+        let mut map = BiMap::new();
+        map.insert_key("abc", 1);
+
+        assert_eq!(map.try_extend(vec![("def", 2)]), Ok(()));
+        assert_eq!(
+            map.try_extend(vec![("ghi", 1), ("jkl", 3)]),
+            Err(vec![Conflict { pair: ("ghi", 1), side: Side::Right }])
+        );
+        assert_eq!(map.len(), 3);
+    }
+}
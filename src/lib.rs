@@ -1,95 +1,706 @@
-use std::collections::HashMap;
-use std::hash::Hash;
+#[cfg(feature = "serde")]
+extern crate serde;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct BiMap<T: Clone + Copy + Eq + Hash, U: Clone + Copy + Eq + Hash> {
-    left_to_right: HashMap<T, U>,
-    right_to_left: HashMap<U, T>,
+use std::collections::{HashMap, TryReserveError};
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+pub use btree::{BiBTreeMap, RangeLeft, RangeRight};
+pub use concurrent::{ConcurrentBiMap, SharedUpdateError};
+pub use compose::{ComposeError, Composed, ComposedIter, Unmatched};
+pub use diff::{Change, PatchError};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use iter::{Drain, FromIterError, IntoIter, Iter, LeftValues, RightValues};
+pub use multi::{BiMultiMap, Links, PartnerIter, Partners};
+pub use persistent::PersistentBiMap;
+pub use set::{Difference, Intersection, SymmetricDifference, Union};
+pub use store::{Hashed, Lookup, Ordered, Storage, Store};
+pub use surjective::{Groups, Pairs, SurjectiveMap};
+pub use transaction::Transaction;
+pub use view::{View, ViewMut};
+
+mod btree;
+mod compose;
+mod concurrent;
+mod diff;
+mod entry;
+mod hamt;
+mod iter;
+mod multi;
+mod persistent;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "serde")]
+pub mod serde_seq;
+mod set;
+mod store;
+mod surjective;
+mod transaction;
+mod view;
+
+/// A one-to-one mapping between values of type `T` ("left") and `U` ("right").
+///
+/// Every element is stored once and shared between both directions, so neither side needs to be
+/// `Copy` or `Clone`. The `Storage` parameters pick the map used for each direction: `LS` for
+/// looking up left values and `RS` for right values. Both are hashed by default; `RS` defaults to
+/// whatever `LS` is, so `BiMap<T, U, Ordered>` is ordered on both sides.
+pub struct BiMap<T, U, LS = Hashed, RS = LS>
+where
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    left_to_right: LS::Map,
+    right_to_left: RS::Map,
 }
 
-impl<T: Copy + Eq + Hash, U: Copy + Eq + Hash> BiMap<T, U> {
+impl<T: Eq + Hash, U: Eq + Hash> BiMap<T, U> {
     pub fn new() -> BiMap<T, U> {
+        BiMap::default()
+    }
+    /// An empty map with room for at least `capacity` pairs before reallocating.
+    pub fn with_capacity(capacity: usize) -> BiMap<T, U> {
+        BiMap::with_capacity_and_hasher(capacity, Default::default(), Default::default())
+    }
+}
+
+impl<T, U, LH, RH> BiMap<T, U, Hashed<LH>, Hashed<RH>>
+where
+    T: Eq + Hash,
+    U: Eq + Hash,
+    LH: BuildHasher + Clone,
+    RH: BuildHasher + Clone,
+{
+    /// An empty map that hashes left values with `left` and right values with `right`.
+    pub fn with_hasher(left: LH, right: RH) -> BiMap<T, U, Hashed<LH>, Hashed<RH>> {
+        BiMap::with_capacity_and_hasher(0, left, right)
+    }
+    /// Like `with_hasher`, with room for at least `capacity` pairs before reallocating.
+    pub fn with_capacity_and_hasher(
+        capacity: usize,
+        left: LH,
+        right: RH,
+    ) -> BiMap<T, U, Hashed<LH>, Hashed<RH>> {
         BiMap {
-            left_to_right: HashMap::new(),
-            right_to_left: HashMap::new(),
+            left_to_right: HashMap::with_capacity_and_hasher(capacity, left),
+            right_to_left: HashMap::with_capacity_and_hasher(capacity, right),
         }
     }
-    pub fn get_key(&self, l: &T) -> Option<&U> {
+    pub fn left_hasher(&self) -> &LH {
+        self.left_to_right.hasher()
+    }
+    pub fn right_hasher(&self) -> &RH {
+        self.right_to_left.hasher()
+    }
+    /// The number of pairs the map can hold without reallocating either direction.
+    pub fn capacity(&self) -> usize {
+        self.left_to_right.capacity().min(self.right_to_left.capacity())
+    }
+    /// Reserves room for at least `additional` more pairs in both directions.
+    pub fn reserve(&mut self, additional: usize) {
+        self.left_to_right.reserve(additional);
+        self.right_to_left.reserve(additional);
+    }
+    /// Like `reserve`, but returns an error instead of aborting if allocation fails.
+    ///
+    /// The map's contents are unchanged either way.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.left_to_right.try_reserve(additional)?;
+        self.right_to_left.try_reserve(additional)
+    }
+    pub fn shrink_to_fit(&mut self) {
+        self.left_to_right.shrink_to_fit();
+        self.right_to_left.shrink_to_fit();
+    }
+    /// Shrinks both directions, keeping room for at least `min_capacity` pairs.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.left_to_right.shrink_to(min_capacity);
+        self.right_to_left.shrink_to(min_capacity);
+    }
+}
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// The partner of a left value, looked up by any form the left values borrow as, such as
+    /// `&str` for `String`.
+    pub fn get_key<Q: ?Sized>(&self, l: &Q) -> Option<&U>
+    where
+        LS::Map: Lookup<T, U, Q>,
+    {
         get(&self.left_to_right, l)
     }
-    pub fn get_value(&self, r: &U) -> Option<&T> {
+    /// The partner of a right value, looked up by any form the right values borrow as.
+    pub fn get_value<Q: ?Sized>(&self, r: &Q) -> Option<&T>
+    where
+        RS::Map: Lookup<U, T, Q>,
+    {
         get(&self.right_to_left, r)
     }
+    pub fn contains_left<Q: ?Sized>(&self, l: &Q) -> bool
+    where
+        LS::Map: Lookup<T, U, Q>,
+    {
+        self.left_to_right.contains_key(l)
+    }
+    pub fn contains_right<Q: ?Sized>(&self, r: &Q) -> bool
+    where
+        RS::Map: Lookup<U, T, Q>,
+    {
+        self.right_to_left.contains_key(r)
+    }
     pub fn insert_key(&mut self, l: T, r: U) {
-        insert(&mut self.left_to_right, &mut self.right_to_left, l, r);
+        if let Err(error) = self.try_insert(l, r) {
+            panic!("{}", error);
+        }
     }
     pub fn insert_value(&mut self, r: U, l: T) {
-        insert(&mut self.right_to_left, &mut self.left_to_right, r, l);
+        self.insert_key(l, r);
+    }
+    /// Inserts the pair `(l, r)`, unless either value is already bound to a different partner.
+    ///
+    /// Inserting a pair that is already present succeeds without changing the map.
+    pub fn try_insert(&mut self, l: T, r: U) -> Result<(), InsertError<'_, T, U>> {
+        try_insert(&mut self.left_to_right, &mut self.right_to_left, l, r)
+    }
+    pub fn try_insert_value(&mut self, r: U, l: T) -> Result<(), InsertError<'_, T, U>> {
+        self.try_insert(l, r)
+    }
+    /// Inserts the pair `(l, r)`, first removing any pairs that contain `l` or `r`.
+    pub fn insert_overwrite(&mut self, l: T, r: U) -> Overwritten<T, U> {
+        insert_overwrite(&mut self.left_to_right, &mut self.right_to_left, l, r)
+    }
+    /// Binds a left value to `r`, returning its previous partner.
+    ///
+    /// When `l` is not yet in the map, the pair `(l, r)` is inserted and `None` is returned.
+    /// Otherwise the stored left value is kept and `l` is dropped.
+    pub fn update_key(&mut self, l: T, r: U) -> Option<U> {
+        match self.try_update_key(l, r) {
+            Ok(old) => old,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    /// Binds a right value to `l`, returning its previous partner.
+    ///
+    /// When `r` is not yet in the map, the pair `(l, r)` is inserted and `None` is returned.
+    pub fn update_value(&mut self, r: U, l: T) -> Option<T> {
+        match self.try_update_value(r, l) {
+            Ok(old) => old,
+            Err(error) => panic!("{}", error),
+        }
     }
-    pub fn update_key(&mut self, l: &T, r: U) -> Option<U> {
+    /// Like `update_key`, but leaves the map unchanged if `r` is bound to a left value other than
+    /// `l`.
+    pub fn try_update_key(&mut self, l: T, r: U) -> Result<Option<U>, UpdateError<'_, U, T>> {
         update(&mut self.left_to_right, &mut self.right_to_left, l, r)
     }
-    pub fn update_value(&mut self, r: &U, l: T) -> Option<T> {
+    /// Like `update_value`, but leaves the map unchanged if `l` is bound to a right value other
+    /// than `r`.
+    pub fn try_update_value(&mut self, r: U, l: T) -> Result<Option<T>, UpdateError<'_, T, U>> {
         update(&mut self.right_to_left, &mut self.left_to_right, r, l)
     }
-    pub fn remove(&mut self, l: &T) -> Option<U> {
+    /// Lets `f` change the partner of a left value, then indexes the pair by the new partner.
+    ///
+    /// `f` works on a copy, so the map is left unchanged if the changed partner is already bound to
+    /// a different left value. Returns whether `l` was found.
+    pub fn modify_right<Q, F>(&mut self, l: &Q, f: F) -> Result<bool, UpdateError<'_, U, T>>
+    where
+        U: Clone,
+        Q: ?Sized,
+        F: FnOnce(&mut U),
+        LS::Map: Lookup<T, U, Q>,
+    {
+        modify(&mut self.left_to_right, &mut self.right_to_left, l, f)
+    }
+    /// Lets `f` change the partner of a right value, then indexes the pair by the new partner.
+    ///
+    /// Like `modify_right`, the map is left unchanged if the changed partner clashes.
+    pub fn modify_left<Q, F>(&mut self, r: &Q, f: F) -> Result<bool, UpdateError<'_, T, U>>
+    where
+        T: Clone,
+        Q: ?Sized,
+        F: FnOnce(&mut T),
+        RS::Map: Lookup<U, T, Q>,
+    {
+        modify(&mut self.right_to_left, &mut self.left_to_right, r, f)
+    }
+    /// Exchanges the partners of two left values, returning whether both were found.
+    ///
+    /// Nothing changes if either is missing.
+    pub fn swap_right<Q: ?Sized>(&mut self, l1: &Q, l2: &Q) -> bool
+    where
+        LS::Map: Lookup<T, U, Q>,
+    {
+        swap(&mut self.left_to_right, &mut self.right_to_left, l1, l2)
+    }
+    /// Exchanges the partners of two right values, returning whether both were found.
+    pub fn swap_left<Q: ?Sized>(&mut self, r1: &Q, r2: &Q) -> bool
+    where
+        RS::Map: Lookup<U, T, Q>,
+    {
+        swap(&mut self.right_to_left, &mut self.left_to_right, r1, r2)
+    }
+    /// Removes the pair holding a left value, looked up like in `get_key`, returning its partner.
+    pub fn remove<Q: ?Sized>(&mut self, l: &Q) -> Option<U>
+    where
+        LS::Map: Lookup<T, U, Q>,
+    {
         remove(&mut self.left_to_right, &mut self.right_to_left, l)
     }
-    pub fn remove_value(&mut self, r: &U) -> Option<T> {
+    /// Removes the pair holding a right value, looked up like in `get_value`, returning its
+    /// partner.
+    pub fn remove_value<Q: ?Sized>(&mut self, r: &Q) -> Option<T>
+    where
+        RS::Map: Lookup<U, T, Q>,
+    {
         remove(&mut self.right_to_left, &mut self.left_to_right, r)
     }
     pub fn len(&self) -> usize {
         self.left_to_right.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.left_to_right.is_empty()
+    }
+    /// The same pairs with left and right swapped, without copying or rehashing anything.
+    pub fn inverse(self) -> BiMap<U, T, RS, LS> {
+        BiMap {
+            left_to_right: self.right_to_left,
+            right_to_left: self.left_to_right,
+        }
+    }
+    /// Replaces every left value `l` with `f(l)`, keeping the storage of each side.
+    ///
+    /// Fails if `f` maps two left values to the same one: the first pair is kept and the others
+    /// are reported.
+    pub fn map_left<V, F>(self, mut f: F) -> Rebuilt<V, U, LS, RS>
+    where
+        V: Eq,
+        F: FnMut(T) -> V,
+        LS: Storage<V, U>,
+        RS: Storage<U, V>,
+        <LS as Storage<V, U>>::Map: Default,
+        <RS as Storage<U, V>>::Map: Default,
+    {
+        BiMap::try_from_iter(self.into_iter().map(|(l, r)| (f(l), r)))
+    }
+    /// Replaces every right value `r` with `f(r)`, failing like `map_left` if `f` maps two right
+    /// values to the same one.
+    pub fn map_right<V, F>(self, mut f: F) -> Rebuilt<T, V, LS, RS>
+    where
+        V: Eq,
+        F: FnMut(U) -> V,
+        LS: Storage<T, V>,
+        RS: Storage<V, T>,
+        <LS as Storage<T, V>>::Map: Default,
+        <RS as Storage<V, T>>::Map: Default,
+    {
+        BiMap::try_from_iter(self.into_iter().map(|(l, r)| (l, f(r))))
+    }
+}
+
+/// A map built from pairs of which some may have clashed.
+type Rebuilt<T, U, LS, RS> = Result<BiMap<T, U, LS, RS>, FromIterError<T, U, LS, RS>>;
+
+impl<T, U, LS, RS> Default for BiMap<T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    LS::Map: Default,
+    RS::Map: Default,
+{
+    fn default() -> BiMap<T, U, LS, RS> {
+        BiMap {
+            left_to_right: Default::default(),
+            right_to_left: Default::default(),
+        }
+    }
+}
+
+/// Cloning copies every element, rather than sharing them with the original, so that each element
+/// is only ever referenced from the two maps of a single `BiMap`. Hashers are cloned along.
+impl<T, U, LS, RS> Clone for BiMap<T, U, LS, RS>
+where
+    T: Clone + Eq,
+    U: Clone + Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn clone(&self) -> BiMap<T, U, LS, RS> {
+        let mut map = BiMap {
+            left_to_right: self.left_to_right.empty(),
+            right_to_left: self.right_to_left.empty(),
+        };
+        for (l, r) in self.iter() {
+            insert(&mut map.left_to_right, &mut map.right_to_left, T::clone(l), U::clone(r));
+        }
+        map
+    }
+}
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> PartialEq for BiMap<T, U, LS, RS> {
+    fn eq(&self, other: &BiMap<T, U, LS, RS>) -> bool {
+        self.len() == other.len() && self.iter().all(|(l, r)| other.get_key(l) == Some(r))
+    }
+}
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> Eq for BiMap<T, U, LS, RS> {}
+
+impl<T, U, LS, RS> fmt::Debug for BiMap<T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// The pairs removed from a `BiMap` by `insert_overwrite`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Overwritten<T, U> {
+    /// Neither value was in the map.
+    Neither,
+    /// The left value was bound to another right value; this is the pair that held it.
+    Left(T, U),
+    /// The right value was bound to another left value; this is the pair that held it.
+    Right(T, U),
+    /// Both values were bound, in two different pairs: the one holding the left value comes first.
+    Both((T, U), (T, U)),
+    /// The pair itself was already in the map.
+    Pair(T, U),
+}
+
+/// The reason a pair could not be inserted into a `BiMap`.
+///
+/// Each variant gives back the rejected pair and refers to the partners it clashed with.
+#[derive(Debug, Eq, PartialEq)]
+pub enum InsertError<'a, T, U> {
+    /// The left value is already bound to `partner`.
+    Left { pair: (T, U), partner: &'a U },
+    /// The right value is already bound to `partner`.
+    Right { pair: (T, U), partner: &'a T },
+    /// The left value is bound to `left_partner` and the right value to `right_partner`.
+    Both {
+        pair: (T, U),
+        left_partner: &'a U,
+        right_partner: &'a T,
+    },
+}
+
+impl<'a, T, U> InsertError<'a, T, U> {
+    pub fn pair(&self) -> (&T, &U) {
+        match self {
+            InsertError::Left { pair, .. }
+            | InsertError::Right { pair, .. }
+            | InsertError::Both { pair, .. } => (&pair.0, &pair.1),
+        }
+    }
+    pub fn into_pair(self) -> (T, U) {
+        match self {
+            InsertError::Left { pair, .. }
+            | InsertError::Right { pair, .. }
+            | InsertError::Both { pair, .. } => pair,
+        }
+    }
+    pub fn side(&self) -> Side {
+        match self {
+            InsertError::Left { .. } => Side::Left,
+            InsertError::Right { .. } => Side::Right,
+            InsertError::Both { .. } => Side::Both,
+        }
+    }
+}
+
+impl<'a, T, U> fmt::Display for InsertError<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.side(), f)
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> Error for InsertError<'a, T, U> {}
+
+/// Which half of a rejected pair was already bound to a different partner.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+    Both,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Side::Left => "left value is already bound to a different right value",
+            Side::Right => "right value is already bound to a different left value",
+            Side::Both => "left and right values are already bound to different partners",
+        })
+    }
+}
+
+/// A rejected pair, without references into the map it clashed with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Conflict<T, U> {
+    pub pair: (T, U),
+    pub side: Side,
+}
+
+impl<'a, T, U> From<InsertError<'a, T, U>> for Conflict<T, U> {
+    fn from(error: InsertError<'a, T, U>) -> Conflict<T, U> {
+        Conflict {
+            side: error.side(),
+            pair: error.into_pair(),
+        }
+    }
+}
+
+impl<T, U> fmt::Display for Conflict<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.side, f)
+    }
+}
+
+impl<T: fmt::Debug, U: fmt::Debug> Error for Conflict<T, U> {}
+
+/// The reason an update was rejected: the new `value` is already bound to `partner`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct UpdateError<'a, V, P> {
+    pub value: V,
+    pub partner: &'a P,
+}
+
+impl<'a, V, P> fmt::Display for UpdateError<'a, V, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("new partner is already bound to a different value")
+    }
 }
 
-fn get<'a, T: Copy + Eq + Hash, U: Copy + Eq + Hash>(
-    map: &'a HashMap<T, U>,
-    key: &T,
-) -> Option<&'a U> {
-    map.get(key)
+impl<'a, V: fmt::Debug, P: fmt::Debug> Error for UpdateError<'a, V, P> {}
+
+fn get<'a, T, U, Q: ?Sized, M: Lookup<T, U, Q>>(map: &'a M, key: &Q) -> Option<&'a U> {
+    map.get(key).map(|value| &**value)
 }
 
-fn insert<T: Copy + Eq + Hash, U: Copy + Eq + Hash>(
-    mut map1: &mut HashMap<T, U>,
-    mut map2: &mut HashMap<U, T>,
+/// Inserts a pair of which neither half is in the maps yet.
+fn insert<T, U, M1: Store<T, U>, M2: Store<U, T>>(map1: &mut M1, map2: &mut M2, v1: T, v2: U) {
+    let (v1, v2) = (Arc::new(v1), Arc::new(v2));
+    map1.insert(Arc::clone(&v1), Arc::clone(&v2));
+    map2.insert(v2, v1);
+}
+
+fn try_insert<'a, T: Eq, U: Eq, M1: Store<T, U>, M2: Store<U, T>>(
+    map1: &'a mut M1,
+    map2: &'a mut M2,
     v1: T,
     v2: U,
-) {
-    assert!(
-        !map1.contains_key(&v1) && !map2.contains_key(&v2) ||
-            map1.get(&v1).is_some() && map1.get(&v1).unwrap() == &v2
-    );
-    map1.insert(v1, v2);
-    map2.insert(v2, v1);
+) -> Result<(), InsertError<'a, T, U>> {
+    if Lookup::get(map1, &v1).is_some_and(|existing| **existing == v2) {
+        return Ok(());
+    }
+    match (map1.contains_key(&v1), map2.contains_key(&v2)) {
+        (false, false) => {
+            insert(map1, map2, v1, v2);
+            Ok(())
+        }
+        (true, false) => Err(InsertError::Left {
+            partner: get(map1, &v1).unwrap(),
+            pair: (v1, v2),
+        }),
+        (false, true) => Err(InsertError::Right {
+            partner: get(map2, &v2).unwrap(),
+            pair: (v1, v2),
+        }),
+        (true, true) => Err(InsertError::Both {
+            left_partner: get(map1, &v1).unwrap(),
+            right_partner: get(map2, &v2).unwrap(),
+            pair: (v1, v2),
+        }),
+    }
+}
+
+fn insert_overwrite<T: Eq, U: Eq, M1: Store<T, U>, M2: Store<U, T>>(
+    map1: &mut M1,
+    map2: &mut M2,
+    v1: T,
+    v2: U,
+) -> Overwritten<T, U> {
+    let by_v1 = remove_entry(map1, map2, &v1);
+    let by_v2 = remove_entry(map2, map1, &v2);
+    let overwritten = match (by_v1, by_v2) {
+        (None, None) => Overwritten::Neither,
+        (Some((old_v1, old_v2)), None) => {
+            if old_v2 == v2 {
+                Overwritten::Pair(old_v1, old_v2)
+            } else {
+                Overwritten::Left(old_v1, old_v2)
+            }
+        }
+        (None, Some((old_v2, old_v1))) => Overwritten::Right(old_v1, old_v2),
+        (Some(by_v1), Some((old_v2, old_v1))) => Overwritten::Both(by_v1, (old_v1, old_v2)),
+    };
+    insert(map1, map2, v1, v2);
+    overwritten
 }
 
-fn update<T: Copy + Eq + Hash, U: Copy + Eq + Hash>(
-    mut map1: &mut HashMap<T, U>,
-    mut map2: &mut HashMap<U, T>,
+/// Binds `v1` to `v2`, inserting the pair if `v1` is not in the map yet.
+fn update<'a, T: Eq, U, M1: Store<T, U>, M2: Store<U, T>>(
+    map1: &mut M1,
+    map2: &'a mut M2,
+    v1: T,
+    v2: U,
+) -> Result<Option<U>, UpdateError<'a, U, T>> {
+    if map2.get(&v2).is_some_and(|partner| **partner != v1) {
+        return Err(UpdateError {
+            partner: get(map2, &v2).unwrap(),
+            value: v2,
+        });
+    }
+    let (v1, old_v2) = match remove_entry(map1, map2, &v1) {
+        Some((stored, old_v2)) => (stored, Some(old_v2)),
+        None => (v1, None),
+    };
+    insert(map1, map2, v1, v2);
+    Ok(old_v2)
+}
+
+/// Binds `v1` to `v2`, doing nothing if `v1` is not in the map.
+fn rebind<'a, T: Eq, U, M1: Store<T, U>, M2: Store<U, T>>(
+    map1: &mut M1,
+    map2: &'a mut M2,
     v1: &T,
     v2: U,
-) -> Option<U> {
-    let old_v2 = remove(map1, map2, v1);
-    insert(map1, map2, *v1, v2);
-    old_v2
+) -> Result<Option<U>, UpdateError<'a, U, T>> {
+    if !map1.contains_key(v1) {
+        return Ok(None);
+    }
+    if map2.get(&v2).is_some_and(|partner| **partner != *v1) {
+        return Err(UpdateError {
+            partner: get(map2, &v2).unwrap(),
+            value: v2,
+        });
+    }
+    let (v1, old_v2) = remove_entry(map1, map2, v1).unwrap();
+    insert(map1, map2, v1, v2);
+    Ok(Some(old_v2))
 }
 
-fn remove<T: Copy + Eq + Hash, U: Copy + Eq + Hash>(
-    mut map1: &mut HashMap<T, U>,
-    mut map2: &mut HashMap<U, T>,
-    key: &T,
-) -> Option<U> {
-    if let Some(value) = map1.get(key) {
-        map2.remove(value);
+fn modify<'a, T, U, Q, M1, M2, F>(
+    map1: &mut M1,
+    map2: &'a mut M2,
+    key: &Q,
+    f: F,
+) -> Result<bool, UpdateError<'a, U, T>>
+where
+    U: Clone + Eq,
+    Q: ?Sized,
+    M1: Store<T, U> + Lookup<T, U, Q>,
+    M2: Store<U, T>,
+    F: FnOnce(&mut U),
+{
+    let old = match get(map1, key) {
+        Some(old) => old,
+        None => return Ok(false),
+    };
+    let mut new = old.clone();
+    f(&mut new);
+    if new == *old {
+        return Ok(true);
+    }
+    if map2.contains_key(&new) {
+        return Err(UpdateError {
+            partner: get(map2, &new).unwrap(),
+            value: new,
+        });
+    }
+    let (v1, _) = remove_entry(map1, map2, key).unwrap();
+    insert(map1, map2, v1, new);
+    Ok(true)
+}
+
+fn swap<T, U, Q, M1, M2>(map1: &mut M1, map2: &mut M2, key1: &Q, key2: &Q) -> bool
+where
+    Q: ?Sized,
+    M1: Store<T, U> + Lookup<T, U, Q>,
+    M2: Store<U, T>,
+{
+    if !map1.contains_key(key1) || !map1.contains_key(key2) {
+        return false;
     }
-    map1.remove(key)
+    let (v1, w1) = map1.remove_entry(key1).unwrap();
+    let (v2, w2) = match map1.remove_entry(key2) {
+        Some(entry) => entry,
+        // Both keys are the same value.
+        None => {
+            map1.insert(v1, w1);
+            return true;
+        }
+    };
+    map1.insert(Arc::clone(&v1), Arc::clone(&w2));
+    map1.insert(Arc::clone(&v2), Arc::clone(&w1));
+    map2.insert(w2, v1);
+    map2.insert(w1, v2);
+    true
+}
+
+fn retain<T, U, M1: Store<T, U>, M2: Store<U, T>, F: FnMut(&T, &U) -> bool>(
+    map1: &mut M1,
+    map2: &mut M2,
+    mut f: F,
+) {
+    map1.retain(|v1, v2| {
+        let keep = f(v1, v2);
+        if !keep {
+            map2.remove_entry(v2);
+        }
+        keep
+    });
+}
+
+fn remove<T, U, Q: ?Sized, M1: Lookup<T, U, Q>, M2: Store<U, T>>(
+    map1: &mut M1,
+    map2: &mut M2,
+    key: &Q,
+) -> Option<U> {
+    remove_entry(map1, map2, key).map(|(_, value)| value)
+}
+
+fn remove_entry<T, U, Q: ?Sized, M1: Lookup<T, U, Q>, M2: Store<U, T>>(
+    map1: &mut M1,
+    map2: &mut M2,
+    key: &Q,
+) -> Option<(T, U)> {
+    let (v1, v2) = map1.remove_entry(key)?;
+    map2.remove_entry(&v2);
+    Some((unwrap(v1), unwrap(v2)))
 }
 
+/// Takes an element back out of its shared allocation, once it has been removed from both maps.
+fn unwrap<T>(value: Arc<T>) -> T {
+    Arc::into_inner(value).expect("element is still referenced by the other map")
+}
 
 #[cfg(test)]
 mod tests {
-    use super::BiMap;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+    use super::{BiMap, Hashed, InsertError, Ordered, Overwritten, Side, UpdateError};
+
+    /// A hasher without a `Default`, so that it can only be passed in.
+    #[derive(Clone)]
+    struct Seeded(u64);
+
+    impl BuildHasher for Seeded {
+        type Hasher = DefaultHasher;
+
+        fn build_hasher(&self) -> DefaultHasher {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u64(self.0);
+            hasher
+        }
+    }
 
     #[test]
     fn create() {
@@ -134,6 +745,59 @@ mod tests {
         map.insert_key("abc", "123");
     }
 
+    #[test]
+    fn try_insert() {
+        let mut map: BiMap<&str, &str> = BiMap::new();
+
+        assert_eq!(map.try_insert("abc", "xyz"), Ok(()));
+        assert_eq!(map.try_insert("abc", "xyz"), Ok(()));
+        assert_eq!(map.try_insert_value("123", "def"), Ok(()));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn try_insert_conflict() {
+        let mut map: BiMap<&str, &str> = BiMap::new();
+        map.insert_key("abc", "xyz");
+        map.insert_key("def", "123");
+
+        assert_eq!(
+            map.try_insert("abc", "456"),
+            Err(InsertError::Left { pair: ("abc", "456"), partner: &"xyz" })
+        );
+        assert_eq!(
+            map.try_insert_value("xyz", "ghi"),
+            Err(InsertError::Right { pair: ("ghi", "xyz"), partner: &"abc" })
+        );
+        let error = map.try_insert("abc", "123").unwrap_err();
+        assert_eq!(
+            error,
+            InsertError::Both { pair: ("abc", "123"), left_partner: &"xyz", right_partner: &"def" }
+        );
+        assert_eq!(error.into_pair(), ("abc", "123"));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn insert_overwrite() {
+        let mut map: BiMap<&str, &str> = BiMap::new();
+
+        assert_eq!(map.insert_overwrite("abc", "xyz"), Overwritten::Neither);
+        assert_eq!(map.insert_overwrite("abc", "xyz"), Overwritten::Pair("abc", "xyz"));
+        assert_eq!(map.insert_overwrite("abc", "123"), Overwritten::Left("abc", "xyz"));
+        assert_eq!(map.insert_overwrite("def", "123"), Overwritten::Right("abc", "123"));
+        map.insert_key("ghi", "456");
+        assert_eq!(
+            map.insert_overwrite("def", "456"),
+            Overwritten::Both(("def", "123"), ("ghi", "456"))
+        );
+
+        assert_eq!(map.get_key(&"def"), Some(&"456"));
+        assert_eq!(map.get_value(&"456"), Some(&"def"));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.left_to_right.len(), map.right_to_left.len());
+    }
+
     #[test]
     fn remove() {
         let mut map: BiMap<&str, &str> = BiMap::new();
@@ -179,7 +843,7 @@ mod tests {
     fn update() {
         let mut map: BiMap<&str, &str> = BiMap::new();
         map.insert_key("abc", "xyz");
-        map.update_key(&"abc", "def");
+        map.update_key("abc", "def");
 
         assert_eq!(map.get_key(&"abc"), Some(&"def"));
         assert_eq!(map.get_value(&"xyz"), None);
@@ -188,6 +852,208 @@ mod tests {
         assert_eq!(map.left_to_right.len(), map.right_to_left.len());
     }
 
+    #[test]
+    fn update_conflict() {
+        let mut map: BiMap<&str, &str> = BiMap::new();
+        map.insert_key("abc", "xyz");
+        map.insert_key("def", "123");
+
+        assert_eq!(
+            map.try_update_key("abc", "123"),
+            Err(UpdateError { value: "123", partner: &"def" })
+        );
+        assert_eq!(
+            map.try_update_value("xyz", "def"),
+            Err(UpdateError { value: "def", partner: &"123" })
+        );
+        assert_eq!(map.get_key(&"abc"), Some(&"xyz"));
+        assert_eq!(map.get_key(&"def"), Some(&"123"));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.try_update_key("abc", "xyz"), Ok(Some("xyz")));
+        assert_eq!(map.try_update_value("123", "ghi"), Ok(Some("def")));
+        assert_eq!(map.get_value(&"123"), Some(&"ghi"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_other_value() {
+        let mut map: BiMap<&str, &str> = BiMap::new();
+        map.insert_key("abc", "xyz");
+        map.insert_key("def", "123");
+        map.update_key("abc", "123");
+    }
+
+    #[test]
+    fn update_absent() {
+        let mut map: BiMap<&str, &str> = BiMap::new();
+
+        assert_eq!(map.update_key("abc", "def"), None);
+        assert_eq!(map.get_key(&"abc"), Some(&"def"));
+        assert_eq!(map.get_value(&"def"), Some(&"abc"));
+        assert_eq!(map.try_update_value("def", "ghi"), Ok(Some("abc")));
+        assert!(map.try_update_value("xyz", "ghi").is_err());
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn modify() {
+        let mut map: BiMap<String, String> = BiMap::new();
+        map.insert_key("abc".to_string(), "xyz".to_string());
+        map.insert_key("def".to_string(), "123".to_string());
+
+        assert_eq!(map.modify_right("abc", |r| r.push('!')), Ok(true));
+        assert_eq!(map.get_key("abc").map(|r| &r[..]), Some("xyz!"));
+        assert_eq!(map.get_value("xyz!").map(|l| &l[..]), Some("abc"));
+        assert!(!map.contains_right("xyz"));
+
+        assert_eq!(
+            map.modify_right("abc", |r| *r = "123".to_string()),
+            Err(UpdateError { value: "123".to_string(), partner: &"def".to_string() })
+        );
+        assert_eq!(map.get_key("abc").map(|r| &r[..]), Some("xyz!"));
+        assert_eq!(map.modify_right("abc", |_| ()), Ok(true));
+        assert_eq!(map.modify_right("ghi", |_| panic!("ghi is not in the map")), Ok(false));
+
+        assert_eq!(map.modify_left("123", |l| l.make_ascii_uppercase()), Ok(true));
+        assert_eq!(map.get_value("123").map(|l| &l[..]), Some("DEF"));
+        assert!(map.modify_left("123", |l| *l = "abc".to_string()).is_err());
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn swap() {
+        let mut map: BiMap<String, u32, Hashed, Ordered> = BiMap::default();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+        map.insert_key("ghi".to_string(), 3);
+
+        assert!(map.swap_right("abc", "def"));
+        assert_eq!(map.get_key("abc"), Some(&2));
+        assert_eq!(map.get_value(&1).map(|l| &l[..]), Some("def"));
+
+        assert!(map.swap_left(&2, &3));
+        assert_eq!(map.get_key("ghi"), Some(&2));
+        assert_eq!(map.get_value(&3).map(|l| &l[..]), Some("abc"));
+
+        assert!(map.swap_right("ghi", "ghi"));
+        assert!(!map.swap_right("abc", "jkl"));
+        assert!(!map.swap_left(&4, &1));
+        assert_eq!(map.get_key("ghi"), Some(&2));
+        assert_eq!(map.get_key("abc"), Some(&3));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn inverse() {
+        let mut map: BiMap<&str, u32, Hashed, Ordered> = BiMap::default();
+        map.insert_key("abc", 2);
+        map.insert_key("def", 1);
+        let map = map.inverse();
+
+        assert_eq!(map.get_key(&1), Some(&"def"));
+        assert_eq!(map.get_value(&"abc"), Some(&2));
+        assert_eq!(map.first_left(), Some((&1, &"def")));
+        assert_eq!(map.inverse().get_key(&"abc"), Some(&2));
+    }
+
+    #[test]
+    fn map_left_right() {
+        let map: BiMap<&str, u32> = vec![("abc", 1), ("de", 2), ("f", 3)].into_iter().collect();
+
+        let lengths = map.clone().map_left(str::len).unwrap();
+        assert_eq!(lengths.get_key(&2), Some(&2));
+        assert_eq!(lengths.get_value(&3), Some(&1));
+        let names = map.clone().map_right(|r| r.to_string()).unwrap();
+        assert_eq!(names.get_key("abc").map(|r| &r[..]), Some("1"));
+
+        let error = map.map_right(|r| r % 2).unwrap_err();
+        assert_eq!(error.conflicts.len(), 1);
+        assert_eq!(error.conflicts[0].side, Side::Right);
+        assert_eq!(error.map.len(), 2);
+    }
+
+    #[test]
+    fn owned() {
+        let mut map: BiMap<String, Vec<u8>> = BiMap::new();
+        map.insert_key("abc".to_string(), vec![1, 2, 3]);
+        map.insert_value(vec![4, 5], "def".to_string());
+
+        assert_eq!(map.get_key(&"abc".to_string()), Some(&vec![1, 2, 3]));
+        assert_eq!(map.get_value(&vec![4, 5]), Some(&"def".to_string()));
+        assert_eq!(map.update_key("abc".to_string(), vec![6]), Some(vec![1, 2, 3]));
+        assert_eq!(map.remove_value(&vec![6]), Some("abc".to_string()));
+        assert_eq!(map.remove(&"def".to_string()), Some(vec![4, 5]));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn borrowed() {
+        let mut map: BiMap<String, Vec<u8>> = BiMap::new();
+        map.insert_key("abc".to_string(), vec![1, 2]);
+        map.insert_key("def".to_string(), vec![3]);
+
+        assert_eq!(map.get_key("abc"), Some(&vec![1, 2]));
+        assert_eq!(map.get_value(&[3][..]), Some(&"def".to_string()));
+        assert!(map.contains_left("def"));
+        assert!(!map.contains_right(&[4][..]));
+        assert_eq!(map.remove("abc"), Some(vec![1, 2]));
+        assert_eq!(map.remove_value(&[3][..]), Some("def".to_string()));
+        assert!(map.is_empty());
+
+        let mut map: BiMap<String, Vec<u8>, Hashed, Ordered> = BiMap::default();
+        map.insert_key("abc".to_string(), vec![1, 2]);
+
+        assert_eq!(map.get_value(&[1, 2][..]), Some(&"abc".to_string()));
+        assert_eq!(map.remove_value(&[1, 2][..]), Some("abc".to_string()));
+        assert!(!map.contains_left("abc"));
+    }
+
+    #[test]
+    fn with_hasher() {
+        let mut map = BiMap::with_hasher(Seeded(1), Seeded(2));
+        map.insert_key("abc", "xyz");
+        let clone = map.clone();
+
+        assert_eq!(map.get_key(&"abc"), Some(&"xyz"));
+        assert_eq!(clone.get_value(&"xyz"), Some(&"abc"));
+        assert_eq!(clone.left_hasher().0, 1);
+        assert_eq!(clone.right_hasher().0, 2);
+    }
+
+    #[test]
+    fn default_hasher() {
+        type Fixed = Hashed<BuildHasherDefault<DefaultHasher>>;
+        let map: BiMap<&str, u32, Fixed, Fixed> =
+            vec![("abc", 1), ("def", 2)].into_iter().collect();
+        let other: BiMap<&str, u32, Fixed, Fixed> =
+            BiMap::with_capacity_and_hasher(2, Default::default(), Default::default());
+
+        assert_eq!(map.get_key(&"def"), Some(&2));
+        assert_ne!(map, other);
+        assert!(other.left_to_right.capacity() >= 2);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut map: BiMap<u32, u32> = BiMap::with_capacity(10);
+        assert!(map.capacity() >= 10);
+
+        map.reserve(100);
+        assert!(map.capacity() >= 100);
+        map.insert_key(1, 2);
+        map.shrink_to(50);
+        assert!(map.capacity() >= 50);
+        map.shrink_to_fit();
+        assert!(map.capacity() >= 1);
+        assert_eq!(map.get_key(&1), Some(&2));
+
+        assert!(map.try_reserve(1000).is_ok());
+        assert!(map.capacity() >= 1001);
+        assert!(map.try_reserve(usize::MAX).is_err());
+        assert_eq!(map.get_value(&2), Some(&1));
+    }
+
     #[test]
     fn eq() {
         let mut map1: BiMap<&str, &str> = BiMap::new();
@@ -0,0 +1,481 @@
+use std::borrow::Borrow;
+use std::collections::{hash_map, hash_set, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::iter::{FromIterator, FusedIterator};
+use std::sync::Arc;
+
+use super::store::{Query, Ref};
+
+/// A many-to-many relation between values of type `T` ("left") and `U` ("right").
+///
+/// Each left value can be linked to any number of right values and the other way around. Like in a
+/// `BiMap`, every element is stored once and shared between both directions, however many links
+/// it takes part in. Elements without links are not kept.
+pub struct BiMultiMap<T, U> {
+    left_to_rights: Index<T, U>,
+    right_to_lefts: Index<U, T>,
+    len: usize,
+}
+
+/// One direction of a `BiMultiMap`: every element on one side with the set of its partners.
+pub(crate) type Index<K, V> = HashMap<Ref<K>, HashSet<Ref<V>>>;
+
+impl<T: Eq + Hash, U: Eq + Hash> BiMultiMap<T, U> {
+    pub fn new() -> BiMultiMap<T, U> {
+        BiMultiMap::default()
+    }
+    /// Links `l` to `r`, returning whether they were not linked yet.
+    pub fn insert(&mut self, l: T, r: U) -> bool {
+        if self.contains(&l, &r) {
+            return false;
+        }
+        let l = match existing(&self.left_to_rights, &l) {
+            Some(l) => l,
+            None => Arc::new(l),
+        };
+        let r = match existing(&self.right_to_lefts, &r) {
+            Some(r) => r,
+            None => Arc::new(r),
+        };
+        link(&mut self.left_to_rights, &mut self.right_to_lefts, l, r);
+        self.len += 1;
+        true
+    }
+    /// Unlinks `l` from `r`, returning whether they were linked.
+    pub fn remove<Q1, Q2>(&mut self, l: &Q1, r: &Q2) -> bool
+    where
+        T: Borrow<Q1>,
+        U: Borrow<Q2>,
+        Q1: ?Sized + Eq + Hash,
+        Q2: ?Sized + Eq + Hash,
+    {
+        if !unlink(&mut self.left_to_rights, Query::new(l), Query::new(r)) {
+            return false;
+        }
+        unlink(&mut self.right_to_lefts, Query::new(r), Query::new(l));
+        self.len -= 1;
+        true
+    }
+    /// Removes every link of a left value, returning how many there were.
+    pub fn remove_left<Q: ?Sized + Eq + Hash>(&mut self, l: &Q) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        let removed = remove_all(&mut self.left_to_rights, &mut self.right_to_lefts, l);
+        self.len -= removed;
+        removed
+    }
+    /// Removes every link of a right value, returning how many there were.
+    pub fn remove_right<Q: ?Sized + Eq + Hash>(&mut self, r: &Q) -> usize
+    where
+        U: Borrow<Q>,
+    {
+        let removed = remove_all(&mut self.right_to_lefts, &mut self.left_to_rights, r);
+        self.len -= removed;
+        removed
+    }
+    /// The right values linked to a left value, which are none if it is not in the map.
+    pub fn get_rights<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> Partners<'_, U>
+    where
+        T: Borrow<Q>,
+    {
+        Partners {
+            set: self.left_to_rights.get(Query::new(l)),
+        }
+    }
+    /// The left values linked to a right value, which are none if it is not in the map.
+    pub fn get_lefts<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> Partners<'_, T>
+    where
+        U: Borrow<Q>,
+    {
+        Partners {
+            set: self.right_to_lefts.get(Query::new(r)),
+        }
+    }
+    pub fn contains<Q1, Q2>(&self, l: &Q1, r: &Q2) -> bool
+    where
+        T: Borrow<Q1>,
+        U: Borrow<Q2>,
+        Q1: ?Sized + Eq + Hash,
+        Q2: ?Sized + Eq + Hash,
+    {
+        self.get_rights(l).contains(r)
+    }
+    pub fn contains_left<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.left_to_rights.contains_key(Query::new(l))
+    }
+    pub fn contains_right<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> bool
+    where
+        U: Borrow<Q>,
+    {
+        self.right_to_lefts.contains_key(Query::new(r))
+    }
+    /// The number of links.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The number of distinct left values.
+    pub fn left_len(&self) -> usize {
+        self.left_to_rights.len()
+    }
+    /// The number of distinct right values.
+    pub fn right_len(&self) -> usize {
+        self.right_to_lefts.len()
+    }
+    pub fn clear(&mut self) {
+        self.left_to_rights.clear();
+        self.right_to_lefts.clear();
+        self.len = 0;
+    }
+    /// An iterator over all links, grouped by left value.
+    pub fn iter(&self) -> Links<'_, T, U> {
+        Links {
+            outer: self.left_to_rights.iter(),
+            inner: None,
+            len: self.len,
+        }
+    }
+}
+
+/// The element of `map` equal to `key`, if any, so that a new link can share it.
+pub(crate) fn existing<K: Eq + Hash, V>(map: &Index<K, V>, key: &K) -> Option<Arc<K>> {
+    map.get_key_value(Query::new(key)).map(|(key, _)| Arc::clone(&key.0))
+}
+
+/// Links two elements in both directions.
+fn link<T: Eq + Hash, U: Eq + Hash>(
+    map1: &mut Index<T, U>,
+    map2: &mut Index<U, T>,
+    v1: Arc<T>,
+    v2: Arc<U>,
+) {
+    map1.entry(Ref(Arc::clone(&v1))).or_default().insert(Ref(Arc::clone(&v2)));
+    map2.entry(Ref(v2)).or_default().insert(Ref(v1));
+}
+
+/// Unlinks `key` from `partner` in one direction, dropping `key` once it has no partners left.
+pub(crate) fn unlink<K, V, Q1, Q2>(map: &mut Index<K, V>, key: &Q1, partner: &Q2) -> bool
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    Ref<K>: Borrow<Q1>,
+    Ref<V>: Borrow<Q2>,
+    Q1: ?Sized + Eq + Hash,
+    Q2: ?Sized + Eq + Hash,
+{
+    let partners = match map.get_mut(key) {
+        Some(partners) => partners,
+        None => return false,
+    };
+    if !partners.remove(partner) {
+        return false;
+    }
+    if partners.is_empty() {
+        map.remove(key);
+    }
+    true
+}
+
+/// Removes `key` and all of its links from both directions, returning how many links there were.
+fn remove_all<T, U, Q>(map1: &mut Index<T, U>, map2: &mut Index<U, T>, key: &Q) -> usize
+where
+    T: Eq + Hash + Borrow<Q>,
+    U: Eq + Hash,
+    Q: ?Sized + Eq + Hash,
+{
+    let (key, partners) = match map1.remove_entry(Query::new(key)) {
+        Some(entry) => entry,
+        None => return 0,
+    };
+    for partner in &partners {
+        unlink(map2, partner, &key);
+    }
+    partners.len()
+}
+
+impl<T, U> Default for BiMultiMap<T, U> {
+    fn default() -> BiMultiMap<T, U> {
+        BiMultiMap {
+            left_to_rights: HashMap::new(),
+            right_to_lefts: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+/// Like cloning a `BiMap`, this copies every element once, however many links it takes part in.
+impl<T: Clone + Eq + Hash, U: Clone + Eq + Hash> Clone for BiMultiMap<T, U> {
+    fn clone(&self) -> BiMultiMap<T, U> {
+        let mut map = BiMultiMap::new();
+        for (l, rights) in &self.left_to_rights {
+            let l = Arc::new(T::clone(l));
+            for r in rights {
+                let r = match existing(&map.right_to_lefts, &**r) {
+                    Some(r) => r,
+                    None => Arc::new(U::clone(r)),
+                };
+                link(&mut map.left_to_rights, &mut map.right_to_lefts, Arc::clone(&l), r);
+            }
+        }
+        map.len = self.len;
+        map
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> PartialEq for BiMultiMap<T, U> {
+    fn eq(&self, other: &BiMultiMap<T, U>) -> bool {
+        self.len == other.len && self.iter().all(|(l, r)| other.contains(l, r))
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> Eq for BiMultiMap<T, U> {}
+
+impl<T: Eq + Hash + fmt::Debug, U: Eq + Hash + fmt::Debug> fmt::Debug for BiMultiMap<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups = self.left_to_rights.iter().map(|(l, rights)| {
+            let rights = Partners { set: Some(rights) };
+            (&**l, rights)
+        });
+        f.debug_map().entries(groups).finish()
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> FromIterator<(T, U)> for BiMultiMap<T, U> {
+    fn from_iter<I: IntoIterator<Item = (T, U)>>(iter: I) -> BiMultiMap<T, U> {
+        let mut map = BiMultiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> Extend<(T, U)> for BiMultiMap<T, U> {
+    fn extend<I: IntoIterator<Item = (T, U)>>(&mut self, iter: I) {
+        for (l, r) in iter {
+            self.insert(l, r);
+        }
+    }
+}
+
+impl<'a, T: Eq + Hash, U: Eq + Hash> IntoIterator for &'a BiMultiMap<T, U> {
+    type Item = (&'a T, &'a U);
+    type IntoIter = Links<'a, T, U>;
+
+    fn into_iter(self) -> Links<'a, T, U> {
+        self.iter()
+    }
+}
+
+/// The partners of one element of a `BiMultiMap`, created by `BiMultiMap::get_rights` or
+/// `BiMultiMap::get_lefts`.
+pub struct Partners<'a, V: 'a> {
+    set: Option<&'a HashSet<Ref<V>>>,
+}
+
+impl<'a, V: Eq + Hash> Partners<'a, V> {
+    pub(crate) fn new(set: Option<&'a HashSet<Ref<V>>>) -> Partners<'a, V> {
+        Partners { set }
+    }
+    pub fn len(&self) -> usize {
+        self.set.map_or(0, HashSet::len)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn contains<Q: ?Sized + Eq + Hash>(&self, v: &Q) -> bool
+    where
+        V: Borrow<Q>,
+    {
+        self.set.is_some_and(|set| set.contains(Query::new(v)))
+    }
+    pub fn iter(&self) -> PartnerIter<'a, V> {
+        PartnerIter {
+            inner: self.set.map(HashSet::iter),
+        }
+    }
+}
+
+impl<'a, V> Clone for Partners<'a, V> {
+    fn clone(&self) -> Partners<'a, V> {
+        *self
+    }
+}
+
+impl<'a, V> Copy for Partners<'a, V> {}
+
+impl<'a, V: Eq + Hash + fmt::Debug> fmt::Debug for Partners<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, V: Eq + Hash> IntoIterator for Partners<'a, V> {
+    type Item = &'a V;
+    type IntoIter = PartnerIter<'a, V>;
+
+    fn into_iter(self) -> PartnerIter<'a, V> {
+        self.iter()
+    }
+}
+
+/// Iterator over the partners of one element, created by `Partners::iter`.
+pub struct PartnerIter<'a, V: 'a> {
+    inner: Option<hash_set::Iter<'a, Ref<V>>>,
+}
+
+impl<'a, V> Iterator for PartnerIter<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.as_mut()?.next().map(|v| &**v)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, V> ExactSizeIterator for PartnerIter<'a, V> {
+    fn len(&self) -> usize {
+        self.inner.as_ref().map_or(0, ExactSizeIterator::len)
+    }
+}
+
+impl<'a, V> FusedIterator for PartnerIter<'a, V> {}
+
+impl<'a, V> Clone for PartnerIter<'a, V> {
+    fn clone(&self) -> PartnerIter<'a, V> {
+        PartnerIter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, V: fmt::Debug> fmt::Debug for PartnerIter<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the links of a `BiMultiMap`, created by `BiMultiMap::iter`.
+pub struct Links<'a, T: 'a, U: 'a> {
+    outer: hash_map::Iter<'a, Ref<T>, HashSet<Ref<U>>>,
+    inner: Option<(&'a T, hash_set::Iter<'a, Ref<U>>)>,
+    len: usize,
+}
+
+impl<'a, T, U> Iterator for Links<'a, T, U> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        loop {
+            if let Some((l, rights)) = &mut self.inner {
+                if let Some(r) = rights.next() {
+                    self.len -= 1;
+                    return Some((*l, &**r));
+                }
+            }
+            let (l, rights) = self.outer.next()?;
+            self.inner = Some((&**l, rights.iter()));
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, U> ExactSizeIterator for Links<'a, T, U> {}
+
+impl<'a, T, U> FusedIterator for Links<'a, T, U> {}
+
+impl<'a, T, U> Clone for Links<'a, T, U> {
+    fn clone(&self) -> Links<'a, T, U> {
+        Links {
+            outer: self.outer.clone(),
+            inner: self.inner.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for Links<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::BiMultiMap;
+
+    #[test]
+    fn insert() {
+        let mut map: BiMultiMap<&str, u32> =
+            vec![("rust", 1), ("rust", 2), ("web", 2), ("web", 3), ("db", 3)].into_iter().collect();
+
+        assert_eq!((map.len(), map.left_len(), map.right_len()), (5, 3, 3));
+        assert!(!map.insert("rust", 1));
+        assert!(map.insert("db", 1));
+        let rights: HashSet<_> = map.get_rights("db").iter().cloned().collect();
+        assert_eq!(rights, vec![1, 3].into_iter().collect());
+        assert_eq!(map.get_lefts(&1).len(), 2);
+        assert!(map.get_lefts(&1).contains("db"));
+        assert!(map.contains("web", &3));
+        assert!(map.get_rights("missing").is_empty());
+        assert_eq!(map.iter().len(), 6);
+    }
+
+    #[test]
+    fn remove() {
+        let mut map: BiMultiMap<&str, u32> =
+            vec![("rust", 1), ("rust", 2), ("web", 2), ("web", 3), ("db", 3)].into_iter().collect();
+
+        assert!(map.remove("web", &2));
+        assert!(!map.remove("web", &2));
+        assert_eq!(map.get_rights("web").iter().collect::<Vec<_>>(), vec![&3]);
+        let rights: HashSet<_> = map.get_rights("rust").iter().cloned().collect();
+        assert_eq!(rights, vec![1, 2].into_iter().collect());
+
+        assert!(map.remove("db", &3));
+        assert!(!map.contains_left("db"));
+        assert!(map.contains_right(&3));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn remove_all() {
+        let mut map: BiMultiMap<&str, u32> =
+            vec![("rust", 1), ("rust", 2), ("web", 2), ("web", 3), ("db", 3)].into_iter().collect();
+
+        assert_eq!(map.remove_left("rust"), 2);
+        assert_eq!(map.remove_left("rust"), 0);
+        assert!(!map.contains_right(&1));
+        assert_eq!(map.get_lefts(&2).iter().collect::<Vec<_>>(), vec![&"web"]);
+
+        assert_eq!(map.remove_right(&3), 2);
+        assert_eq!((map.len(), map.left_len(), map.right_len()), (1, 1, 1));
+        assert!(!map.contains_left("db"));
+    }
+
+    #[test]
+    fn clone() {
+        let mut map = BiMultiMap::new();
+        map.insert("web".to_string(), 2);
+        map.insert("web".to_string(), 3);
+        map.insert("db".to_string(), 3);
+        let mut copy = map.clone();
+
+        assert_eq!(copy, map);
+        copy.remove_right(&2);
+        assert_ne!(copy, map);
+        assert_eq!(format!("{:?}", copy.get_rights("web")), "{3}");
+    }
+}
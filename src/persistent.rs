@@ -0,0 +1,336 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, FusedIterator};
+use std::sync::Arc;
+
+use super::hamt::{self, Hamt};
+use super::{Conflict, Side};
+
+/// An immutable one-to-one mapping between values of type `T` ("left") and `U` ("right").
+///
+/// Both directions are hash array mapped tries whose nodes are shared between versions. Instead of
+/// changing the map, `try_insert`, `insert_overwrite` and `remove` return a new version that shares
+/// everything but the changed paths with the old one. Cloning, and so keeping any number of old
+/// versions around, costs the same as cloning an `Arc`.
+pub struct PersistentBiMap<T, U, S = RandomState> {
+    left_to_right: Hamt<T, U>,
+    right_to_left: Hamt<U, T>,
+    hasher: S,
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> PersistentBiMap<T, U> {
+    pub fn new() -> PersistentBiMap<T, U> {
+        PersistentBiMap::with_hasher(RandomState::new())
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash, S: BuildHasher + Clone> PersistentBiMap<T, U, S> {
+    /// An empty map that hashes both sides with `hasher`, which all versions derived from it share.
+    pub fn with_hasher(hasher: S) -> PersistentBiMap<T, U, S> {
+        PersistentBiMap {
+            left_to_right: Hamt::new(),
+            right_to_left: Hamt::new(),
+            hasher,
+        }
+    }
+    pub fn get_key<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> Option<&U>
+    where
+        T: Borrow<Q>,
+    {
+        get(&self.left_to_right, &self.hasher, l)
+    }
+    pub fn get_value<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> Option<&T>
+    where
+        U: Borrow<Q>,
+    {
+        get(&self.right_to_left, &self.hasher, r)
+    }
+    pub fn contains_left<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.get_key(l).is_some()
+    }
+    pub fn contains_right<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> bool
+    where
+        U: Borrow<Q>,
+    {
+        self.get_value(r).is_some()
+    }
+    pub fn len(&self) -> usize {
+        self.left_to_right.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// A version with the pair `(l, r)` inserted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either value is already bound to a different partner.
+    pub fn insert_key(&self, l: T, r: U) -> PersistentBiMap<T, U, S> {
+        match self.try_insert(l, r) {
+            Ok(map) => map,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    /// A version with the pair `(l, r)` inserted, unless either value is already bound to a
+    /// different partner.
+    pub fn try_insert(&self, l: T, r: U) -> Result<PersistentBiMap<T, U, S>, Conflict<T, U>> {
+        let side = match (self.get_key(&l), self.contains_right(&r)) {
+            (Some(old), _) if *old == r => return Ok(self.clone()),
+            (None, false) => return Ok(self.with_pair(l, r)),
+            (Some(_), false) => Side::Left,
+            (None, true) => Side::Right,
+            (Some(_), true) => Side::Both,
+        };
+        Err(Conflict { pair: (l, r), side })
+    }
+    /// A version with the pair `(l, r)` inserted, without any pairs that contained `l` or `r`.
+    pub fn insert_overwrite(&self, l: T, r: U) -> PersistentBiMap<T, U, S> {
+        self.remove(&l).remove_value(&r).with_pair(l, r)
+    }
+    /// A version without the pair holding a left value.
+    pub fn remove<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> PersistentBiMap<T, U, S>
+    where
+        T: Borrow<Q>,
+    {
+        match remove(&self.left_to_right, &self.right_to_left, &self.hasher, l) {
+            Some((left_to_right, right_to_left)) => PersistentBiMap {
+                left_to_right,
+                right_to_left,
+                hasher: self.hasher.clone(),
+            },
+            None => self.clone(),
+        }
+    }
+    /// A version without the pair holding a right value.
+    pub fn remove_value<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> PersistentBiMap<T, U, S>
+    where
+        U: Borrow<Q>,
+    {
+        match remove(&self.right_to_left, &self.left_to_right, &self.hasher, r) {
+            Some((right_to_left, left_to_right)) => PersistentBiMap {
+                left_to_right,
+                right_to_left,
+                hasher: self.hasher.clone(),
+            },
+            None => self.clone(),
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T, U> {
+        Iter {
+            inner: self.left_to_right.iter(),
+        }
+    }
+    /// A version with a pair of which neither half is in the map yet.
+    fn with_pair(&self, l: T, r: U) -> PersistentBiMap<T, U, S> {
+        let (left, right) = (self.hasher.hash_one(&l), self.hasher.hash_one(&r));
+        let (l, r) = (Arc::new(l), Arc::new(r));
+        PersistentBiMap {
+            left_to_right: self.left_to_right.insert(left, Arc::clone(&l), Arc::clone(&r)).0,
+            right_to_left: self.right_to_left.insert(right, r, l).0,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+fn get<'a, T, U, Q, S>(map: &'a Hamt<T, U>, hasher: &S, key: &Q) -> Option<&'a U>
+where
+    T: Eq + Borrow<Q>,
+    Q: ?Sized + Eq + Hash,
+    S: BuildHasher,
+{
+    map.get(hasher.hash_one(key), key).map(|value| &**value)
+}
+
+/// Both directions without the pair holding `key`, or `None` if it is not in the map.
+fn remove<T, U, Q, S>(
+    map1: &Hamt<T, U>,
+    map2: &Hamt<U, T>,
+    hasher: &S,
+    key: &Q,
+) -> Option<(Hamt<T, U>, Hamt<U, T>)>
+where
+    T: Eq + Borrow<Q>,
+    U: Eq + Hash,
+    Q: ?Sized + Eq + Hash,
+    S: BuildHasher,
+{
+    let (map1, (_, partner)) = map1.remove(hasher.hash_one(key), key)?;
+    let (map2, _) = map2
+        .remove(hasher.hash_one(&*partner), &*partner)
+        .expect("pair is in both directions");
+    Some((map1, map2))
+}
+
+/// Cloning shares the whole map with the original, and so takes constant time.
+impl<T, U, S: Clone> Clone for PersistentBiMap<T, U, S> {
+    fn clone(&self) -> PersistentBiMap<T, U, S> {
+        PersistentBiMap {
+            left_to_right: self.left_to_right.clone(),
+            right_to_left: self.right_to_left.clone(),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash, S: BuildHasher + Default + Clone> Default
+    for PersistentBiMap<T, U, S>
+{
+    fn default() -> PersistentBiMap<T, U, S> {
+        PersistentBiMap::with_hasher(S::default())
+    }
+}
+
+impl<T, U, S> PartialEq for PersistentBiMap<T, U, S>
+where
+    T: Eq + Hash,
+    U: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn eq(&self, other: &PersistentBiMap<T, U, S>) -> bool {
+        self.len() == other.len() && self.iter().all(|(l, r)| other.get_key(l) == Some(r))
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash, S: BuildHasher + Clone> Eq for PersistentBiMap<T, U, S> {}
+
+impl<T, U, S> fmt::Debug for PersistentBiMap<T, U, S>
+where
+    T: Eq + Hash + fmt::Debug,
+    U: Eq + Hash + fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T, U, S> FromIterator<(T, U)> for PersistentBiMap<T, U, S>
+where
+    T: Eq + Hash,
+    U: Eq + Hash,
+    S: BuildHasher + Default + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (T, U)>>(iter: I) -> PersistentBiMap<T, U, S> {
+        let mut map = PersistentBiMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Replaces the map with a new version holding the pairs, overwriting like `BiMap::extend`. Other
+/// versions are not affected.
+impl<T, U, S> Extend<(T, U)> for PersistentBiMap<T, U, S>
+where
+    T: Eq + Hash,
+    U: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn extend<I: IntoIterator<Item = (T, U)>>(&mut self, iter: I) {
+        for (l, r) in iter {
+            *self = self.insert_overwrite(l, r);
+        }
+    }
+}
+
+impl<'a, T, U, S> IntoIterator for &'a PersistentBiMap<T, U, S>
+where
+    T: Eq + Hash,
+    U: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    type Item = (&'a T, &'a U);
+    type IntoIter = Iter<'a, T, U>;
+
+    fn into_iter(self) -> Iter<'a, T, U> {
+        self.iter()
+    }
+}
+
+/// Iterator over the pairs of a `PersistentBiMap`, created by `PersistentBiMap::iter`.
+pub struct Iter<'a, T: 'a, U: 'a> {
+    inner: hamt::Iter<'a, T, U>,
+}
+
+impl<'a, T, U> Iterator for Iter<'a, T, U> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next().map(|(l, r)| (&**l, &**r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U> ExactSizeIterator for Iter<'a, T, U> {}
+
+impl<'a, T, U> FusedIterator for Iter<'a, T, U> {}
+
+impl<'a, T, U> Clone for Iter<'a, T, U> {
+    fn clone(&self) -> Iter<'a, T, U> {
+        Iter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for Iter<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Conflict, Side};
+    use super::PersistentBiMap;
+
+    #[test]
+    fn versions() {
+        let empty = PersistentBiMap::new();
+        let first = empty.insert_key("abc", 1).insert_key("def", 2);
+        let second = first.insert_overwrite("abc", 2);
+        let third = second.remove(&"abc");
+
+        assert_eq!(empty.len(), 0);
+        assert_eq!((first.get_key(&"abc"), first.get_value(&2)), (Some(&1), Some(&"def")));
+        assert_eq!((second.len(), second.get_value(&2)), (1, Some(&"abc")));
+        assert!(!second.contains_left(&"def") && !second.contains_right(&1));
+        assert!(third.is_empty());
+        assert_eq!(third.remove_value(&2), third);
+    }
+
+    #[test]
+    fn try_insert() {
+        let map: PersistentBiMap<_, _> = vec![("abc", 1), ("def", 2)].into_iter().collect();
+
+        assert_eq!(map.try_insert("abc", 1), Ok(map.clone()));
+        assert_eq!(
+            map.try_insert("abc", 2),
+            Err(Conflict { pair: ("abc", 2), side: Side::Both })
+        );
+        assert_eq!(map.try_insert("ghi", 3).map(|map| map.len()), Ok(3));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn shared() {
+        let mut versions = vec![PersistentBiMap::new()];
+        for n in 0..500 {
+            let next = versions[n].insert_overwrite(n % 100, n);
+            versions.push(next);
+        }
+
+        assert_eq!(versions[50].len(), 50);
+        assert_eq!(versions[500].len(), 100);
+        assert_eq!(versions[150].get_key(&20), Some(&120));
+        assert_eq!(versions[500].get_key(&20), Some(&420));
+        assert_eq!(versions[500].get_value(&20), None);
+        assert_eq!(versions[500].iter().len(), 100);
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Error, MapAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::{BiMap, Storage};
+
+/// Encodes the map as a map from left to right values.
+impl<T, U, LS, RS> Serialize for BiMap<T, U, LS, RS>
+where
+    T: Eq + Serialize,
+    U: Eq + Serialize,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Decodes a map from left to right values, rejecting it if any value appears twice.
+impl<'de, T, U, LS, RS> Deserialize<'de> for BiMap<T, U, LS, RS>
+where
+    T: Eq + Deserialize<'de>,
+    U: Eq + Deserialize<'de>,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    LS::Map: Default,
+    RS::Map: Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BiMap<T, U, LS, RS>, D::Error> {
+        deserializer.deserialize_map(BiMapVisitor(PhantomData))
+    }
+}
+
+struct BiMapVisitor<T, U, LS, RS>(PhantomData<(T, U, LS, RS)>);
+
+impl<'de, T, U, LS, RS> Visitor<'de> for BiMapVisitor<T, U, LS, RS>
+where
+    T: Eq + Deserialize<'de>,
+    U: Eq + Deserialize<'de>,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    LS::Map: Default,
+    RS::Map: Default,
+{
+    type Value = BiMap<T, U, LS, RS>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a one-to-one map")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<BiMap<T, U, LS, RS>, A::Error> {
+        let mut map = BiMap::default();
+        let mut index = 0;
+        while let Some((l, r)) = access.next_entry()? {
+            insert(&mut map, index, l, r)?;
+            index += 1;
+        }
+        Ok(map)
+    }
+}
+
+/// Inserts a decoded pair, naming its position in the input if it breaks the one-to-one rule.
+pub(crate) fn insert<T, U, LS, RS, E>(
+    map: &mut BiMap<T, U, LS, RS>,
+    index: usize,
+    l: T,
+    r: U,
+) -> Result<(), E>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    E: Error,
+{
+    map.try_insert(l, r)
+        .map_err(|error| E::custom(format_args!("entry {}: {}", index, error)))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::super::BiMap;
+
+    #[test]
+    fn round_trip() {
+        let mut map: BiMap<String, u32> = BiMap::new();
+        map.insert_key("abc".to_string(), 1);
+        map.insert_key("def".to_string(), 2);
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<BiMap<String, u32>>(&json).unwrap(), map);
+    }
+
+    #[test]
+    fn reject_duplicate_right() {
+        let error = serde_json::from_str::<BiMap<String, u32>>(r#"{"abc": 1, "def": 2, "ghi": 1}"#)
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("entry 2: right value is already bound to a different left value"));
+    }
+}
@@ -0,0 +1,97 @@
+//! Encodes a `BiMap` as a sequence of `(left, right)` pairs, for use with
+//! `#[serde(with = "bidirectional_hashmap::serde_seq")]`.
+//!
+//! Unlike the default map encoding, this works for formats that only allow string keys.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::serde_impl::insert;
+use super::{BiMap, Storage};
+
+pub fn serialize<T, U, LS, RS, Z>(
+    map: &BiMap<T, U, LS, RS>,
+    serializer: Z,
+) -> Result<Z::Ok, Z::Error>
+where
+    T: Eq + Serialize,
+    U: Eq + Serialize,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    Z: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+/// Decodes a sequence of pairs, rejecting it if any value appears twice.
+pub fn deserialize<'de, T, U, LS, RS, D>(deserializer: D) -> Result<BiMap<T, U, LS, RS>, D::Error>
+where
+    T: Eq + Deserialize<'de>,
+    U: Eq + Deserialize<'de>,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    LS::Map: Default,
+    RS::Map: Default,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_seq(PairsVisitor(PhantomData))
+}
+
+struct PairsVisitor<T, U, LS, RS>(PhantomData<(T, U, LS, RS)>);
+
+impl<'de, T, U, LS, RS> Visitor<'de> for PairsVisitor<T, U, LS, RS>
+where
+    T: Eq + Deserialize<'de>,
+    U: Eq + Deserialize<'de>,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+    LS::Map: Default,
+    RS::Map: Default,
+{
+    type Value = BiMap<T, U, LS, RS>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of one-to-one pairs")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<BiMap<T, U, LS, RS>, A::Error> {
+        let mut map = BiMap::default();
+        let mut index = 0;
+        while let Some((l, r)) = access.next_element()? {
+            insert(&mut map, index, l, r)?;
+            index += 1;
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::super::{BiMap, Hashed};
+
+    #[test]
+    fn round_trip() {
+        let mut map: BiMap<u32, Vec<u8>> = BiMap::new();
+        map.insert_key(1, vec![1, 2]);
+        map.insert_key(2, vec![3]);
+
+        let json = super::serialize(&map, serde_json::value::Serializer).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(super::deserialize::<u32, Vec<u8>, Hashed, Hashed, _>(json).unwrap(), map);
+    }
+
+    #[test]
+    fn reject_duplicate_left() {
+        let mut deserializer = serde_json::Deserializer::from_str("[[1, [1]], [1, [2]]]");
+        let error =
+            super::deserialize::<u32, Vec<u8>, Hashed, Hashed, _>(&mut deserializer).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("entry 1: left value is already bound to a different right value"));
+    }
+}
@@ -0,0 +1,369 @@
+use std::fmt;
+use std::iter::FusedIterator;
+
+use super::{BiMap, FromIterError, Hashed, Iter, Storage};
+
+/// Set operations, treating each map as a set of pairs.
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// An iterator over the pairs in either map, each yielded once.
+    ///
+    /// The pairs of `self` come first. Where the maps clash, the pairs of `other` are yielded as
+    /// well, so the result need not be one-to-one; `try_union` reports such clashes instead.
+    pub fn union<'a>(&'a self, other: &'a BiMap<T, U, LS, RS>) -> Union<'a, T, U, LS, RS> {
+        Union {
+            inner: self.iter(),
+            rest: other.difference(self),
+        }
+    }
+    /// An iterator over the pairs that are in both maps.
+    pub fn intersection<'a>(
+        &'a self,
+        other: &'a BiMap<T, U, LS, RS>,
+    ) -> Intersection<'a, T, U, LS, RS> {
+        Intersection {
+            inner: self.iter(),
+            other,
+        }
+    }
+    /// An iterator over the pairs that are in `self` but not in `other`.
+    pub fn difference<'a>(
+        &'a self,
+        other: &'a BiMap<T, U, LS, RS>,
+    ) -> Difference<'a, T, U, LS, RS> {
+        Difference {
+            inner: self.iter(),
+            other,
+        }
+    }
+    /// An iterator over the pairs that are in exactly one of the maps.
+    ///
+    /// Like `union`, this can yield two pairs that share a value.
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a BiMap<T, U, LS, RS>,
+    ) -> SymmetricDifference<'a, T, U, LS, RS> {
+        SymmetricDifference {
+            first: self.difference(other),
+            second: other.difference(self),
+        }
+    }
+    /// Adds the pairs of `other` to `self`, keeping the pairs of `self` where the two clash and
+    /// reporting the pairs of `other` that were left out.
+    pub fn try_union(
+        mut self,
+        other: BiMap<T, U, LS, RS>,
+    ) -> Result<Self, FromIterError<T, U, LS, RS>> {
+        match self.try_extend(other) {
+            Ok(()) => Ok(self),
+            Err(conflicts) => Err(FromIterError { map: self, conflicts }),
+        }
+    }
+    /// Keeps only the pairs that are also in `other`.
+    pub fn into_intersection(mut self, other: &BiMap<T, U, LS, RS>) -> Self {
+        self.retain(|l, r| other.get_key(l) == Some(r));
+        self
+    }
+    /// Removes the pairs that are also in `other`.
+    pub fn into_difference(mut self, other: &BiMap<T, U, LS, RS>) -> Self {
+        self.retain(|l, r| other.get_key(l) != Some(r));
+        self
+    }
+    /// Keeps the pairs that are in exactly one of the maps, reporting the pairs of `other` that
+    /// clash with those of `self`, like `try_union`.
+    pub fn try_symmetric_difference(
+        mut self,
+        mut other: BiMap<T, U, LS, RS>,
+    ) -> Result<Self, FromIterError<T, U, LS, RS>> {
+        self.retain(|l, r| {
+            let shared = other.get_key(l) == Some(r);
+            if shared {
+                other.remove(l);
+            }
+            !shared
+        });
+        self.try_union(other)
+    }
+}
+
+/// Iterator over the pairs in either of two `BiMap`s, created by `BiMap::union`.
+pub struct Union<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed, RS = LS>
+where
+    RS: Storage<U, T> + 'a,
+{
+    inner: Iter<'a, T, U, LS>,
+    rest: Difference<'a, T, U, LS, RS>,
+}
+
+impl<'a, T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> Iterator for Union<'a, T, U, LS, RS> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next().or_else(|| self.rest.next())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inner.len();
+        (len, self.rest.size_hint().1.map(|rest| len + rest))
+    }
+}
+
+impl<'a, T, U, LS, RS> FusedIterator for Union<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+}
+
+impl<'a, T, U, LS: Storage<T, U>, RS: Storage<U, T>> Clone for Union<'a, T, U, LS, RS> {
+    fn clone(&self) -> Union<'a, T, U, LS, RS> {
+        Union {
+            inner: self.inner.clone(),
+            rest: self.rest.clone(),
+        }
+    }
+}
+
+impl<'a, T, U, LS, RS> fmt::Debug for Union<'a, T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the pairs shared by two `BiMap`s, created by `BiMap::intersection`.
+pub struct Intersection<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed, RS = LS>
+where
+    RS: Storage<U, T> + 'a,
+{
+    inner: Iter<'a, T, U, LS>,
+    other: &'a BiMap<T, U, LS, RS>,
+}
+
+impl<'a, T, U, LS, RS> Iterator for Intersection<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        let other = self.other;
+        self.inner.find(|&(l, r)| other.get_key(l) == Some(r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, T, U, LS, RS> FusedIterator for Intersection<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+}
+
+impl<'a, T, U, LS: Storage<T, U>, RS: Storage<U, T>> Clone for Intersection<'a, T, U, LS, RS> {
+    fn clone(&self) -> Intersection<'a, T, U, LS, RS> {
+        Intersection {
+            inner: self.inner.clone(),
+            other: self.other,
+        }
+    }
+}
+
+impl<'a, T, U, LS, RS> fmt::Debug for Intersection<'a, T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the pairs of one `BiMap` that are not in another, created by
+/// `BiMap::difference`.
+pub struct Difference<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed, RS = LS>
+where
+    RS: Storage<U, T> + 'a,
+{
+    inner: Iter<'a, T, U, LS>,
+    other: &'a BiMap<T, U, LS, RS>,
+}
+
+impl<'a, T, U, LS, RS> Iterator for Difference<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        let other = self.other;
+        self.inner.find(|&(l, r)| other.get_key(l) != Some(r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, T, U, LS, RS> FusedIterator for Difference<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+}
+
+impl<'a, T, U, LS: Storage<T, U>, RS: Storage<U, T>> Clone for Difference<'a, T, U, LS, RS> {
+    fn clone(&self) -> Difference<'a, T, U, LS, RS> {
+        Difference {
+            inner: self.inner.clone(),
+            other: self.other,
+        }
+    }
+}
+
+impl<'a, T, U, LS, RS> fmt::Debug for Difference<'a, T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the pairs in exactly one of two `BiMap`s, created by
+/// `BiMap::symmetric_difference`.
+pub struct SymmetricDifference<'a, T: 'a, U: 'a, LS: Storage<T, U> + 'a = Hashed, RS = LS>
+where
+    RS: Storage<U, T> + 'a,
+{
+    first: Difference<'a, T, U, LS, RS>,
+    second: Difference<'a, T, U, LS, RS>,
+}
+
+impl<'a, T, U, LS, RS> Iterator for SymmetricDifference<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.first.next().or_else(|| self.second.next())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = self.first.size_hint().1.zip(self.second.size_hint().1);
+        (0, upper.map(|(first, second)| first + second))
+    }
+}
+
+impl<'a, T, U, LS, RS> FusedIterator for SymmetricDifference<'a, T, U, LS, RS>
+where
+    T: Eq,
+    U: Eq,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+}
+
+impl<'a, T, U, LS, RS> Clone for SymmetricDifference<'a, T, U, LS, RS>
+where
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn clone(&self) -> SymmetricDifference<'a, T, U, LS, RS> {
+        SymmetricDifference {
+            first: self.first.clone(),
+            second: self.second.clone(),
+        }
+    }
+}
+
+impl<'a, T, U, LS, RS> fmt::Debug for SymmetricDifference<'a, T, U, LS, RS>
+where
+    T: Eq + fmt::Debug,
+    U: Eq + fmt::Debug,
+    LS: Storage<T, U>,
+    RS: Storage<U, T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::super::{BiBTreeMap, Conflict, Side};
+
+    #[test]
+    fn lazy() {
+        let map1: BiBTreeMap<u32, char> = vec![(1, 'a'), (2, 'b'), (3, 'c')].into_iter().collect();
+        let map2: BiBTreeMap<u32, char> = vec![(2, 'b'), (3, 'd'), (4, 'e')].into_iter().collect();
+
+        let union: HashSet<_> = map1.union(&map2).collect();
+        let expected = vec![(&1, &'a'), (&2, &'b'), (&3, &'c'), (&3, &'d'), (&4, &'e')];
+        assert_eq!(union, expected.into_iter().collect());
+        let intersection: Vec<_> = map1.intersection(&map2).collect();
+        assert_eq!(intersection, vec![(&2, &'b')]);
+        let difference: HashSet<_> = map1.difference(&map2).collect();
+        assert_eq!(difference, vec![(&1, &'a'), (&3, &'c')].into_iter().collect());
+        let difference: HashSet<_> = map2.difference(&map1).collect();
+        assert_eq!(difference, vec![(&3, &'d'), (&4, &'e')].into_iter().collect());
+        let symmetric: HashSet<_> = map1.symmetric_difference(&map2).collect();
+        let expected = vec![(&1, &'a'), (&3, &'c'), (&3, &'d'), (&4, &'e')];
+        assert_eq!(symmetric, expected.into_iter().collect());
+        assert_eq!(map1.intersection(&map1).count(), 3);
+        assert_eq!(map1.difference(&map1).next(), None);
+    }
+
+    #[test]
+    fn owned() {
+        let map1: BiBTreeMap<u32, char> = vec![(1, 'a'), (2, 'b'), (3, 'c')].into_iter().collect();
+        let map2: BiBTreeMap<u32, char> = vec![(2, 'b'), (3, 'd'), (4, 'e')].into_iter().collect();
+
+        assert_eq!(
+            map1.clone().into_intersection(&map2),
+            vec![(2, 'b')].into_iter().collect()
+        );
+        assert_eq!(
+            map1.clone().into_difference(&map2),
+            vec![(1, 'a'), (3, 'c')].into_iter().collect()
+        );
+
+        let error = map1.clone().try_union(map2.clone()).unwrap_err();
+        assert_eq!(error.conflicts, vec![Conflict { pair: (3, 'd'), side: Side::Left }]);
+        assert_eq!(error.map, vec![(1, 'a'), (2, 'b'), (3, 'c'), (4, 'e')].into_iter().collect());
+
+        let error = map1.clone().try_symmetric_difference(map2.clone()).unwrap_err();
+        assert_eq!(error.conflicts, vec![Conflict { pair: (3, 'd'), side: Side::Left }]);
+        assert_eq!(error.map, vec![(1, 'a'), (3, 'c'), (4, 'e')].into_iter().collect());
+
+        let map3: BiBTreeMap<u32, char> = vec![(5, 'f')].into_iter().collect();
+        assert_eq!(map1.clone().try_union(map3.clone()).unwrap().len(), 4);
+        assert_eq!(map1.try_symmetric_difference(map3).unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,439 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::hash_map::{self, RandomState};
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::iter::{self, FusedIterator};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::sync::Arc;
+
+/// Selects the kind of map that stores one direction of a `BiMap`: from the values `K` on one side
+/// to their partners `V`.
+///
+/// Besides `Hashed` and `Ordered`, any type can be used by implementing this trait, pointing `Map`
+/// at a `Store` of its own.
+pub trait Storage<K, V> {
+    type Map: Store<K, V>;
+}
+
+/// Stores a direction in a `HashMap` hashed by `S`, so that its values need to be `Eq + Hash`.
+pub struct Hashed<S = RandomState>(PhantomData<S>);
+
+/// Stores a direction in a `BTreeMap`, so that its values need to be `Ord` and can be queried by
+/// range.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Ordered;
+
+impl<K: Eq + Hash, V, S: BuildHasher + Clone> Storage<K, V> for Hashed<S> {
+    type Map = HashMap<Ref<K>, Arc<V>, S>;
+}
+
+impl<K: Ord, V> Storage<K, V> for Ordered {
+    type Map = BTreeMap<Ref<K>, Arc<V>>;
+}
+
+/// Looks up entries of a direction by `Q`, a borrowed form of its keys `K`, the way `HashMap::get`
+/// accepts a `&str` for `String` keys.
+///
+/// Every `Store` can be queried by `&K` itself; `Hashed` and `Ordered` also accept any `Q` that `K`
+/// borrows as, provided it hashes or compares the same way.
+pub trait Lookup<K, V, Q: ?Sized> {
+    fn get(&self, key: &Q) -> Option<&Arc<V>>;
+    fn contains_key(&self, key: &Q) -> bool {
+        self.get(key).is_some()
+    }
+    fn remove_entry(&mut self, key: &Q) -> Option<(Arc<K>, Arc<V>)>;
+}
+
+/// One direction of a `BiMap`: a map from each element on one side to its partner.
+///
+/// Both directions share every element through an `Arc`, so implementations should hold on to
+/// the `Arc`s they are given rather than cloning the elements. They must behave like a map: at most
+/// one value per key, `insert` replacing any previous value and `len` counting the keys.
+pub trait Store<K, V>: Lookup<K, V, K> + Sized {
+    type Iter<'a>: Iterator<Item = (&'a Arc<K>, &'a Arc<V>)>
+        + ExactSizeIterator
+        + FusedIterator
+        + Clone
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type Keys<'a>: Iterator<Item = &'a Arc<K>> + ExactSizeIterator + FusedIterator + Clone
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type IntoIter: Iterator<Item = (Arc<K>, Arc<V>)> + ExactSizeIterator + FusedIterator;
+    type Drain<'a>: Iterator<Item = (Arc<K>, Arc<V>)> + ExactSizeIterator + FusedIterator
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    /// An empty map, configured like this one.
+    fn empty(&self) -> Self;
+    fn insert(&mut self, key: Arc<K>, value: Arc<V>);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn clear(&mut self);
+    fn retain<F: FnMut(&K, &V) -> bool>(&mut self, f: F);
+    fn iter(&self) -> Self::Iter<'_>;
+    fn keys(&self) -> Self::Keys<'_>;
+    fn into_iter(self) -> Self::IntoIter;
+    fn drain(&mut self) -> Self::Drain<'_>;
+}
+
+/// The key of a `Hashed` or `Ordered` direction: a shared element, hashed and compared like the
+/// element itself.
+///
+/// Unlike `Arc<K>`, it can be borrowed as a `Query<Q>` for every `Q` that `K` borrows as, which is
+/// what lets the std maps be queried by `&Q`.
+pub struct Ref<K>(pub(crate) Arc<K>);
+
+impl<K> Deref for Ref<K> {
+    type Target = K;
+
+    fn deref(&self) -> &K {
+        &self.0
+    }
+}
+
+impl<K: Hash> Hash for Ref<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        K::hash(self, state);
+    }
+}
+
+impl<K: PartialEq> PartialEq for Ref<K> {
+    fn eq(&self, other: &Ref<K>) -> bool {
+        **self == **other
+    }
+}
+
+impl<K: Eq> Eq for Ref<K> {}
+
+impl<K: PartialOrd> PartialOrd for Ref<K> {
+    fn partial_cmp(&self, other: &Ref<K>) -> Option<Ordering> {
+        K::partial_cmp(self, other)
+    }
+}
+
+impl<K: Ord> Ord for Ref<K> {
+    fn cmp(&self, other: &Ref<K>) -> Ordering {
+        K::cmp(self, other)
+    }
+}
+
+impl<K: Borrow<Q>, Q: ?Sized> Borrow<Query<Q>> for Ref<K> {
+    fn borrow(&self) -> &Query<Q> {
+        Query::new((**self).borrow())
+    }
+}
+
+/// A borrowed form of the keys of a direction, as looked up in the std maps.
+///
+/// `Ref<K>` cannot borrow as `Q` directly, since that would overlap with `Ref<K>: Borrow<Ref<K>>`.
+#[repr(transparent)]
+pub struct Query<Q: ?Sized>(Q);
+
+impl<Q: ?Sized> Query<Q> {
+    pub(crate) fn new(key: &Q) -> &Query<Q> {
+        // SAFETY: `Query` is a transparent wrapper around `Q`.
+        unsafe { &*(key as *const Q as *const Query<Q>) }
+    }
+}
+
+impl<Q: ?Sized + Hash> Hash for Query<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<Q: ?Sized + PartialEq> PartialEq for Query<Q> {
+    fn eq(&self, other: &Query<Q>) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Q: ?Sized + Eq> Eq for Query<Q> {}
+
+impl<Q: ?Sized + PartialOrd> PartialOrd for Query<Q> {
+    fn partial_cmp(&self, other: &Query<Q>) -> Option<Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<Q: ?Sized + Ord> Ord for Query<Q> {
+    fn cmp(&self, other: &Query<Q>) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+/// The entries of an `Ordered` direction whose keys lie in `range`.
+pub(crate) fn range<'a, K, V, Q, R>(
+    map: &'a BTreeMap<Ref<K>, Arc<V>>,
+    range: R,
+) -> btree_map::Range<'a, Ref<K>, Arc<V>>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord,
+    R: RangeBounds<Q>,
+{
+    let bounds = (range.start_bound().map(Query::new), range.end_bound().map(Query::new));
+    map.range::<Query<Q>, (Bound<_>, Bound<_>)>(bounds)
+}
+
+type Entry<'a, K, V> = (&'a Ref<K>, &'a Arc<V>);
+type Unref<'a, K, V, T> = fn(Entry<'a, K, V>) -> T;
+type Owned<K, V> = (Arc<K>, Arc<V>);
+type Unwrap<K, V> = fn((Ref<K>, Arc<V>)) -> Owned<K, V>;
+
+fn entry<'a, K, V>((key, value): Entry<'a, K, V>) -> (&'a Arc<K>, &'a Arc<V>) {
+    (&key.0, value)
+}
+
+fn key<'a, K, V>((key, _): Entry<'a, K, V>) -> &'a Arc<K> {
+    &key.0
+}
+
+fn owned<K, V>((key, value): (Ref<K>, Arc<V>)) -> Owned<K, V> {
+    (key.0, value)
+}
+
+impl<K, V, Q, S> Lookup<K, V, Q> for HashMap<Ref<K>, Arc<V>, S>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: ?Sized + Eq + Hash,
+    S: BuildHasher,
+{
+    fn get(&self, key: &Q) -> Option<&Arc<V>> {
+        self.get(Query::new(key))
+    }
+    fn remove_entry(&mut self, key: &Q) -> Option<(Arc<K>, Arc<V>)> {
+        self.remove_entry(Query::new(key)).map(owned)
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Clone> Store<K, V> for HashMap<Ref<K>, Arc<V>, S> {
+    type Iter<'a>
+        = iter::Map<hash_map::Iter<'a, Ref<K>, Arc<V>>, Unref<'a, K, V, (&'a Arc<K>, &'a Arc<V>)>>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type Keys<'a>
+        = iter::Map<hash_map::Iter<'a, Ref<K>, Arc<V>>, Unref<'a, K, V, &'a Arc<K>>>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type IntoIter = iter::Map<hash_map::IntoIter<Ref<K>, Arc<V>>, Unwrap<K, V>>;
+    type Drain<'a>
+        = iter::Map<hash_map::Drain<'a, Ref<K>, Arc<V>>, Unwrap<K, V>>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn empty(&self) -> Self {
+        HashMap::with_hasher(self.hasher().clone())
+    }
+    fn insert(&mut self, key: Arc<K>, value: Arc<V>) {
+        self.insert(Ref(key), value);
+    }
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn clear(&mut self) {
+        self.clear();
+    }
+    fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        self.retain(|k, v| f(k, v));
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter().map(entry as Unref<K, V, _>)
+    }
+    fn keys(&self) -> Self::Keys<'_> {
+        self.iter().map(key as Unref<K, V, _>)
+    }
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter(self).map(owned as Unwrap<K, V>)
+    }
+    fn drain(&mut self) -> Self::Drain<'_> {
+        self.drain().map(owned as Unwrap<K, V>)
+    }
+}
+
+impl<K, V, Q> Lookup<K, V, Q> for BTreeMap<Ref<K>, Arc<V>>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    fn get(&self, key: &Q) -> Option<&Arc<V>> {
+        self.get(Query::new(key))
+    }
+    fn remove_entry(&mut self, key: &Q) -> Option<(Arc<K>, Arc<V>)> {
+        self.remove_entry(Query::new(key)).map(owned)
+    }
+}
+
+impl<K: Ord, V> Store<K, V> for BTreeMap<Ref<K>, Arc<V>> {
+    type Iter<'a>
+        = iter::Map<btree_map::Iter<'a, Ref<K>, Arc<V>>, Unref<'a, K, V, (&'a Arc<K>, &'a Arc<V>)>>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type Keys<'a>
+        = iter::Map<btree_map::Iter<'a, Ref<K>, Arc<V>>, Unref<'a, K, V, &'a Arc<K>>>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type IntoIter = iter::Map<btree_map::IntoIter<Ref<K>, Arc<V>>, Unwrap<K, V>>;
+    type Drain<'a>
+        = Self::IntoIter
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn empty(&self) -> Self {
+        BTreeMap::new()
+    }
+    fn insert(&mut self, key: Arc<K>, value: Arc<V>) {
+        self.insert(Ref(key), value);
+    }
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn clear(&mut self) {
+        self.clear();
+    }
+    fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        self.retain(|k, v| f(k, v));
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter().map(entry as Unref<K, V, _>)
+    }
+    fn keys(&self) -> Self::Keys<'_> {
+        self.iter().map(key as Unref<K, V, _>)
+    }
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter(self).map(owned as Unwrap<K, V>)
+    }
+    fn drain(&mut self) -> Self::Drain<'_> {
+        Store::into_iter(mem::take(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Borrow;
+    use std::iter;
+    use std::slice;
+    use std::sync::Arc;
+    use std::vec;
+
+    use super::super::BiMap;
+    use super::{Hashed, Lookup, Storage, Store};
+
+    /// Stores a direction as an unsorted list of pairs.
+    struct Linear;
+
+    struct List<K, V>(Vec<(Arc<K>, Arc<V>)>);
+
+    type Pair<'a, K, V> = (&'a Arc<K>, &'a Arc<V>);
+    type Project<'a, K, V, T> = fn(&'a (Arc<K>, Arc<V>)) -> T;
+
+    impl<K: Eq, V> Storage<K, V> for Linear {
+        type Map = List<K, V>;
+    }
+
+    impl<K: Eq, V> Default for List<K, V> {
+        fn default() -> List<K, V> {
+            List(Vec::new())
+        }
+    }
+
+    impl<K: Borrow<Q>, V, Q: ?Sized + Eq> Lookup<K, V, Q> for List<K, V> {
+        fn get(&self, key: &Q) -> Option<&Arc<V>> {
+            self.0.iter().find(|(k, _)| (**k).borrow() == key).map(|(_, v)| v)
+        }
+        fn remove_entry(&mut self, key: &Q) -> Option<(Arc<K>, Arc<V>)> {
+            let index = self.0.iter().position(|(k, _)| (**k).borrow() == key)?;
+            Some(self.0.swap_remove(index))
+        }
+    }
+
+    impl<K: Eq, V> Store<K, V> for List<K, V> {
+        type Iter<'a>
+            = iter::Map<slice::Iter<'a, (Arc<K>, Arc<V>)>, Project<'a, K, V, Pair<'a, K, V>>>
+        where
+            Self: 'a,
+            K: 'a,
+            V: 'a;
+        type Keys<'a>
+            = iter::Map<slice::Iter<'a, (Arc<K>, Arc<V>)>, Project<'a, K, V, &'a Arc<K>>>
+        where
+            Self: 'a,
+            K: 'a,
+            V: 'a;
+        type IntoIter = vec::IntoIter<(Arc<K>, Arc<V>)>;
+        type Drain<'a>
+            = vec::Drain<'a, (Arc<K>, Arc<V>)>
+        where
+            Self: 'a,
+            K: 'a,
+            V: 'a;
+
+        fn empty(&self) -> List<K, V> {
+            List::default()
+        }
+        fn insert(&mut self, key: Arc<K>, value: Arc<V>) {
+            Lookup::<K, V, K>::remove_entry(self, &key);
+            self.0.push((key, value));
+        }
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+        fn clear(&mut self) {
+            self.0.clear();
+        }
+        fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+            self.0.retain(|(k, v)| f(k, v));
+        }
+        fn iter(&self) -> Self::Iter<'_> {
+            self.0.iter().map(|(k, v)| (k, v))
+        }
+        fn keys(&self) -> Self::Keys<'_> {
+            self.0.iter().map(|(k, _)| k)
+        }
+        fn into_iter(self) -> Self::IntoIter {
+            self.0.into_iter()
+        }
+        fn drain(&mut self) -> Self::Drain<'_> {
+            self.0.drain(..)
+        }
+    }
+
+    #[test]
+    fn custom() {
+        let mut map: BiMap<&str, Vec<u8>, Linear, Hashed> = BiMap::default();
+        map.insert_key("abc", vec![1]);
+        map.insert_key("def", vec![2]);
+
+        assert_eq!(map.get_key(&"abc"), Some(&vec![1]));
+        assert_eq!(map.get_value(&vec![2]), Some(&"def"));
+        assert!(map.try_insert("ghi", vec![1]).is_err());
+        assert_eq!(map.update_key("abc", vec![3]), Some(vec![1]));
+        assert_eq!(map.remove_value(&vec![2]), Some("def"));
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"abc", &vec![3])]);
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![("abc", vec![3])]);
+    }
+}
@@ -0,0 +1,358 @@
+use std::borrow::Borrow;
+use std::collections::{hash_map, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::iter::{FromIterator, FusedIterator};
+use std::sync::Arc;
+
+use super::multi::{existing, unlink, Index, Partners};
+use super::store::{Query, Ref};
+use super::unwrap;
+
+/// A one-to-many relation in which every child of type `T` has exactly one parent of type `U`.
+///
+/// Besides looking up the parent of a child, the children of a parent can be looked up as a group.
+/// Each parent is stored in a single `Arc` that every child in its group points to, and each child
+/// is stored once for both its lookup and its group. A parent is dropped along with its last child.
+pub struct SurjectiveMap<T, U> {
+    child_to_parent: HashMap<Ref<T>, Arc<U>>,
+    parent_to_children: Index<U, T>,
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> SurjectiveMap<T, U> {
+    pub fn new() -> SurjectiveMap<T, U> {
+        SurjectiveMap::default()
+    }
+    /// Adds `child` to the group of `parent`, returning whether it is new.
+    ///
+    /// A child that is already in the map is moved to `parent`, like in `reparent`.
+    pub fn insert(&mut self, child: T, parent: U) -> bool {
+        if self.contains_child(&child) {
+            self.reparent(&child, parent);
+            return false;
+        }
+        self.adopt(Arc::new(child), parent);
+        true
+    }
+    /// Moves an existing child to the group of `parent`, returning whether it was found.
+    ///
+    /// The old parent is removed along with its group once this was its last child.
+    pub fn reparent<Q: ?Sized + Eq + Hash>(&mut self, child: &Q, parent: U) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        let (child, old) = match self.child_to_parent.remove_entry(Query::new(child)) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        unlink(&mut self.parent_to_children, &Ref(old), &child);
+        self.adopt(child.0, parent);
+        true
+    }
+    /// Removes a child from its group, returning it.
+    pub fn remove<Q: ?Sized + Eq + Hash>(&mut self, child: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+    {
+        let (child, parent) = self.child_to_parent.remove_entry(Query::new(child))?;
+        unlink(&mut self.parent_to_children, &Ref(parent), &child);
+        Some(unwrap(child.0))
+    }
+    /// Removes a parent along with all of its children, returning them.
+    pub fn remove_group<Q: ?Sized + Eq + Hash>(&mut self, parent: &Q) -> Option<(U, Vec<T>)>
+    where
+        U: Borrow<Q>,
+    {
+        let (parent, children) = self.parent_to_children.remove_entry(Query::new(parent))?;
+        let children = children
+            .into_iter()
+            .map(|child| {
+                self.child_to_parent.remove(&child);
+                unwrap(child.0)
+            })
+            .collect();
+        Some((unwrap(parent.0), children))
+    }
+    pub fn get_parent<Q: ?Sized + Eq + Hash>(&self, child: &Q) -> Option<&U>
+    where
+        T: Borrow<Q>,
+    {
+        self.child_to_parent.get(Query::new(child)).map(|parent| &**parent)
+    }
+    /// The children of a parent, which are none if it is not in the map.
+    pub fn get_children<Q: ?Sized + Eq + Hash>(&self, parent: &Q) -> Partners<'_, T>
+    where
+        U: Borrow<Q>,
+    {
+        Partners::new(self.parent_to_children.get(Query::new(parent)))
+    }
+    /// The number of children of a parent.
+    pub fn group_len<Q: ?Sized + Eq + Hash>(&self, parent: &Q) -> usize
+    where
+        U: Borrow<Q>,
+    {
+        self.get_children(parent).len()
+    }
+    pub fn contains_child<Q: ?Sized + Eq + Hash>(&self, child: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.child_to_parent.contains_key(Query::new(child))
+    }
+    pub fn contains_parent<Q: ?Sized + Eq + Hash>(&self, parent: &Q) -> bool
+    where
+        U: Borrow<Q>,
+    {
+        self.parent_to_children.contains_key(Query::new(parent))
+    }
+    /// The number of children.
+    pub fn len(&self) -> usize {
+        self.child_to_parent.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.child_to_parent.is_empty()
+    }
+    /// The number of parents, which is the number of groups.
+    pub fn parent_len(&self) -> usize {
+        self.parent_to_children.len()
+    }
+    pub fn clear(&mut self) {
+        self.child_to_parent.clear();
+        self.parent_to_children.clear();
+    }
+    /// An iterator over every child with its parent.
+    pub fn iter(&self) -> Pairs<'_, T, U> {
+        Pairs {
+            inner: self.child_to_parent.iter(),
+        }
+    }
+    /// An iterator over every parent with its children.
+    pub fn groups(&self) -> Groups<'_, T, U> {
+        Groups {
+            inner: self.parent_to_children.iter(),
+        }
+    }
+    /// Adds a child that is not in the map yet, sharing `parent` with its siblings.
+    fn adopt(&mut self, child: Arc<T>, parent: U) {
+        let parent = match existing(&self.parent_to_children, &parent) {
+            Some(parent) => parent,
+            None => Arc::new(parent),
+        };
+        self.parent_to_children
+            .entry(Ref(Arc::clone(&parent)))
+            .or_default()
+            .insert(Ref(Arc::clone(&child)));
+        self.child_to_parent.insert(Ref(child), parent);
+    }
+}
+
+impl<T, U> Default for SurjectiveMap<T, U> {
+    fn default() -> SurjectiveMap<T, U> {
+        SurjectiveMap {
+            child_to_parent: HashMap::new(),
+            parent_to_children: HashMap::new(),
+        }
+    }
+}
+
+/// Copies each child and each parent once, so that the children of a group in the clone point to
+/// one shared copy of their parent, as in the original. Nothing is shared with the original.
+impl<T: Clone + Eq + Hash, U: Clone + Eq + Hash> Clone for SurjectiveMap<T, U> {
+    fn clone(&self) -> SurjectiveMap<T, U> {
+        let mut map = SurjectiveMap::new();
+        for (parent, children) in &self.parent_to_children {
+            let parent = Arc::new(U::clone(parent));
+            let group: HashSet<_> = children
+                .iter()
+                .map(|child| Ref(Arc::new(T::clone(child))))
+                .collect();
+            for child in &group {
+                map.child_to_parent.insert(Ref(Arc::clone(&child.0)), Arc::clone(&parent));
+            }
+            map.parent_to_children.insert(Ref(parent), group);
+        }
+        map
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> PartialEq for SurjectiveMap<T, U> {
+    fn eq(&self, other: &SurjectiveMap<T, U>) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(child, parent)| other.get_parent(child) == Some(parent))
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> Eq for SurjectiveMap<T, U> {}
+
+impl<T: Eq + Hash + fmt::Debug, U: Eq + Hash + fmt::Debug> fmt::Debug for SurjectiveMap<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.groups()).finish()
+    }
+}
+
+impl<T: Eq + Hash, U: Eq + Hash> FromIterator<(T, U)> for SurjectiveMap<T, U> {
+    fn from_iter<I: IntoIterator<Item = (T, U)>>(iter: I) -> SurjectiveMap<T, U> {
+        let mut map = SurjectiveMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Children that appear more than once end up with the last parent given for them.
+impl<T: Eq + Hash, U: Eq + Hash> Extend<(T, U)> for SurjectiveMap<T, U> {
+    fn extend<I: IntoIterator<Item = (T, U)>>(&mut self, iter: I) {
+        for (child, parent) in iter {
+            self.insert(child, parent);
+        }
+    }
+}
+
+impl<'a, T: Eq + Hash, U: Eq + Hash> IntoIterator for &'a SurjectiveMap<T, U> {
+    type Item = (&'a T, &'a U);
+    type IntoIter = Pairs<'a, T, U>;
+
+    fn into_iter(self) -> Pairs<'a, T, U> {
+        self.iter()
+    }
+}
+
+/// Iterator over the children of a `SurjectiveMap` with their parents, created by
+/// `SurjectiveMap::iter`.
+pub struct Pairs<'a, T: 'a, U: 'a> {
+    inner: hash_map::Iter<'a, Ref<T>, Arc<U>>,
+}
+
+impl<'a, T, U> Iterator for Pairs<'a, T, U> {
+    type Item = (&'a T, &'a U);
+
+    fn next(&mut self) -> Option<(&'a T, &'a U)> {
+        self.inner.next().map(|(child, parent)| (&**child, &**parent))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, U> ExactSizeIterator for Pairs<'a, T, U> {}
+
+impl<'a, T, U> FusedIterator for Pairs<'a, T, U> {}
+
+impl<'a, T, U> Clone for Pairs<'a, T, U> {
+    fn clone(&self) -> Pairs<'a, T, U> {
+        Pairs {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for Pairs<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Iterator over the parents of a `SurjectiveMap` with their children, created by
+/// `SurjectiveMap::groups`.
+pub struct Groups<'a, T: 'a, U: 'a> {
+    inner: hash_map::Iter<'a, Ref<U>, HashSet<Ref<T>>>,
+}
+
+impl<'a, T: Eq + Hash, U> Iterator for Groups<'a, T, U> {
+    type Item = (&'a U, Partners<'a, T>);
+
+    fn next(&mut self) -> Option<(&'a U, Partners<'a, T>)> {
+        self.inner.next().map(|(parent, children)| (&**parent, Partners::new(Some(children))))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: Eq + Hash, U> ExactSizeIterator for Groups<'a, T, U> {}
+
+impl<'a, T: Eq + Hash, U> FusedIterator for Groups<'a, T, U> {}
+
+impl<'a, T, U> Clone for Groups<'a, T, U> {
+    fn clone(&self) -> Groups<'a, T, U> {
+        Groups {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: Eq + Hash + fmt::Debug, U: fmt::Debug> fmt::Debug for Groups<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::SurjectiveMap;
+
+    #[test]
+    fn insert() {
+        let mut map: SurjectiveMap<u32, &str> =
+            vec![(1, "a"), (2, "a"), (3, "b"), (4, "c"), (5, "c"), (6, "c")].into_iter().collect();
+
+        assert_eq!((map.len(), map.parent_len()), (6, 3));
+        assert_eq!(map.get_parent(&4), Some(&"c"));
+        let children: HashSet<_> = map.get_children("c").iter().cloned().collect();
+        assert_eq!(children, vec![4, 5, 6].into_iter().collect());
+        assert_eq!(map.group_len("a"), 2);
+        assert_eq!(map.group_len("d"), 0);
+
+        assert!(map.insert(7, "d"));
+        assert!(!map.insert(7, "a"));
+        assert!(!map.contains_parent("d"));
+        let children: HashSet<_> = map.get_children("a").iter().cloned().collect();
+        assert_eq!(children, vec![1, 2, 7].into_iter().collect());
+    }
+
+    #[test]
+    fn reparent() {
+        let mut map: SurjectiveMap<u32, &str> =
+            vec![(1, "a"), (2, "a"), (3, "b"), (4, "c"), (5, "c"), (6, "c")].into_iter().collect();
+
+        assert!(map.reparent(&3, "a"));
+        assert!(!map.reparent(&9, "a"));
+        assert!(!map.contains_parent("b"));
+        assert_eq!(map.get_parent(&3), Some(&"a"));
+        assert_eq!(map.group_len("a"), 3);
+
+        assert!(map.reparent(&1, "e"));
+        assert_eq!(map.get_children("e").iter().collect::<Vec<_>>(), vec![&1]);
+        assert_eq!((map.len(), map.parent_len()), (6, 3));
+    }
+
+    #[test]
+    fn remove() {
+        let mut map: SurjectiveMap<u32, &str> =
+            vec![(1, "a"), (2, "a"), (3, "b"), (4, "c"), (5, "c"), (6, "c")].into_iter().collect();
+
+        assert_eq!(map.remove(&3), Some(3));
+        assert_eq!(map.remove(&3), None);
+        assert!(!map.contains_parent("b"));
+
+        let (parent, children) = map.remove_group("c").unwrap();
+        let children: HashSet<_> = children.into_iter().collect();
+        assert_eq!((parent, children), ("c", vec![4, 5, 6].into_iter().collect()));
+        assert_eq!(map.remove_group("c"), None);
+        assert!(!map.contains_child(&5));
+        assert_eq!((map.len(), map.parent_len()), (2, 1));
+    }
+
+    #[test]
+    fn clone() {
+        let map: SurjectiveMap<u32, &str> =
+            vec![(1, "a"), (2, "a"), (3, "b"), (4, "c"), (5, "c"), (6, "c")].into_iter().collect();
+        let mut copy = map.clone();
+
+        assert_eq!(copy, map);
+        copy.reparent(&6, "a");
+        assert_ne!(copy, map);
+        assert_eq!(format!("{:?}", copy.get_children("b")), "{3}");
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{insert, remove_entry, BiMap, BiMultiMap, Conflict, Lookup, Side, Storage, Store};
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// Lets `f` stage a group of changes, then applies them all at once.
+    ///
+    /// The changes are replayed in order without checking anything on the way, so that pairs may
+    /// clash in between, as when two left values swap partners. Only the outcome has to be
+    /// one-to-one: if it is, the map is changed to it; if not, the map is left unchanged and the
+    /// staged pairs that clash with others are returned.
+    ///
+    /// Equal values are found through maps of the same kind as this one's, from each value to a
+    /// number, so the cost grows with the number of staged changes about as inserting that many
+    /// pairs would.
+    pub fn transaction<F, R>(&mut self, f: F) -> Result<R, Vec<Conflict<T, U>>>
+    where
+        F: FnOnce(&mut Transaction<T, U>) -> R,
+        LS: Storage<T, usize>,
+        RS: Storage<U, usize>,
+        Ids<T, LS>: Default,
+        Ids<U, RS>: Default,
+    {
+        let mut tx = Transaction {
+            lefts: Vec::new(),
+            rights: Vec::new(),
+            changes: Vec::new(),
+        };
+        let result = f(&mut tx);
+        let Transaction {
+            mut lefts,
+            mut rights,
+            changes,
+        } = tx;
+
+        // Takes out every pair that a change touches, after the staged values in the arenas.
+        let staged = (lefts.len(), rights.len());
+        let (map1, map2) = (&mut self.left_to_right, &mut self.right_to_left);
+        for l in 0..staged.0 {
+            if let Some((l, r)) = remove_entry(map1, map2, &lefts[l]) {
+                lefts.push(l);
+                rights.push(r);
+            }
+        }
+        for r in 0..staged.1 {
+            if let Some((r, l)) = remove_entry(map2, map1, &rights[r]) {
+                lefts.push(l);
+                rights.push(r);
+            }
+        }
+        let taken: Vec<Pair> = (staged.0..lefts.len()).zip(staged.1..rights.len()).collect();
+        let lefts: Vec<_> = lefts.into_iter().map(Arc::new).collect();
+        let rights: Vec<_> = rights.into_iter().map(Arc::new).collect();
+
+        // Numbers the values, equal ones alike, so that the replay never compares values.
+        let left_ids = intern::<T, Ids<T, LS>>(&lefts);
+        let right_ids = intern::<U, Ids<U, RS>>(&rights);
+
+        let mut pairs = Pairs::default();
+        for &(l, r) in &taken {
+            pairs.insert((left_ids[l], right_ids[r]), (l, r));
+        }
+        for change in changes {
+            match change {
+                Change::Insert(l, r) => pairs.insert((left_ids[l], right_ids[r]), (l, r)),
+                Change::Remove(l) => pairs.remove_left(left_ids[l]),
+                Change::RemoveValue(r) => pairs.remove_right(right_ids[r]),
+                Change::UpdateKey(l, r) => {
+                    pairs.remove_left(left_ids[l]);
+                    pairs.insert((left_ids[l], right_ids[r]), (l, r));
+                }
+                Change::UpdateValue(r, l) => {
+                    pairs.remove_right(right_ids[r]);
+                    pairs.insert((left_ids[l], right_ids[r]), (l, r));
+                }
+            }
+        }
+
+        // The taken pairs were one-to-one, so every clash involves a staged pair.
+        let mut clashes: Vec<(usize, Pair, Side)> = pairs
+            .slots
+            .iter()
+            .filter(|&(_, &(_, (l, _)))| l < staged.0)
+            .filter_map(|(&(l, r), &(order, pair))| {
+                let left = pairs.links.get_rights(&l).len() > 1;
+                let right = pairs.links.get_lefts(&r).len() > 1;
+                let side = match (left, right) {
+                    (false, false) => return None,
+                    (true, false) => Side::Left,
+                    (false, true) => Side::Right,
+                    (true, true) => Side::Both,
+                };
+                Some((order, pair, side))
+            })
+            .collect();
+        clashes.sort_by_key(|&(order, ..)| order);
+
+        let mut lefts: Vec<_> = lefts.into_iter().map(Arc::into_inner).collect();
+        let mut rights: Vec<_> = rights.into_iter().map(Arc::into_inner).collect();
+        let mut take = |(l, r): Pair| (lefts[l].take().unwrap(), rights[r].take().unwrap());
+        let (keep, conflicts) = if clashes.is_empty() {
+            let mut keep: Vec<_> = pairs.slots.into_values().collect();
+            keep.sort_unstable();
+            (keep.into_iter().map(|(_, pair)| pair).collect(), Vec::new())
+        } else {
+            let conflicts = clashes
+                .into_iter()
+                .map(|(_, pair, side)| Conflict {
+                    pair: take(pair),
+                    side,
+                })
+                .collect();
+            (taken, conflicts)
+        };
+        for pair in keep {
+            let (l, r) = take(pair);
+            insert(&mut self.left_to_right, &mut self.right_to_left, l, r);
+        }
+        if conflicts.is_empty() {
+            Ok(result)
+        } else {
+            Err(conflicts)
+        }
+    }
+}
+
+/// The indices of a left and a right value in the arenas of a transaction.
+type Pair = (usize, usize);
+
+/// The map numbering the values of one side, of the same kind as that side of the `BiMap`.
+type Ids<K, S> = <S as Storage<K, usize>>::Map;
+
+/// Numbers `values` in order of first appearance, giving equal values the same number.
+fn intern<K, M: Store<K, usize> + Default>(values: &[Arc<K>]) -> Vec<usize> {
+    let mut ids = M::default();
+    let mut numbered = Vec::with_capacity(values.len());
+    for value in values {
+        let id = match Lookup::get(&ids, &**value) {
+            Some(id) => **id,
+            None => {
+                let id = ids.len();
+                ids.insert(Arc::clone(value), Arc::new(id));
+                id
+            }
+        };
+        numbered.push(id);
+    }
+    numbered
+}
+
+/// The pairs of a transaction being replayed, by the numbers of their values.
+///
+/// Unlike a `BiMap`, a value may be in several pairs at once, which is what the clashes are.
+#[derive(Default)]
+struct Pairs {
+    links: BiMultiMap<usize, usize>,
+    /// The order in which each pair was inserted, and where its values are in the arenas.
+    slots: HashMap<Pair, (usize, Pair)>,
+    inserted: usize,
+}
+
+impl Pairs {
+    /// Inserts a pair, unless an equal one is already there.
+    fn insert(&mut self, (l, r): Pair, values: Pair) {
+        if self.links.insert(l, r) {
+            self.slots.insert((l, r), (self.inserted, values));
+            self.inserted += 1;
+        }
+    }
+    /// Removes every pair holding the left value numbered `l`.
+    fn remove_left(&mut self, l: usize) {
+        for &r in self.links.get_rights(&l) {
+            self.slots.remove(&(l, r));
+        }
+        self.links.remove_left(&l);
+    }
+    /// Removes every pair holding the right value numbered `r`.
+    fn remove_right(&mut self, r: usize) {
+        for &l in self.links.get_lefts(&r) {
+            self.slots.remove(&(l, r));
+        }
+        self.links.remove_right(&r);
+    }
+}
+
+/// The changes staged by the closure passed to `BiMap::transaction`.
+///
+/// Nothing is checked while staging: the changes are applied together, or not at all, once the
+/// closure returns.
+#[derive(Debug)]
+pub struct Transaction<T, U> {
+    lefts: Vec<T>,
+    rights: Vec<U>,
+    changes: Vec<Change>,
+}
+
+/// One staged change, referring to its values by their index in `lefts` and `rights`.
+#[derive(Clone, Copy, Debug)]
+enum Change {
+    Insert(usize, usize),
+    Remove(usize),
+    RemoveValue(usize),
+    UpdateKey(usize, usize),
+    UpdateValue(usize, usize),
+}
+
+impl<T, U> Transaction<T, U> {
+    /// Stages inserting the pair `(l, r)`.
+    pub fn insert(&mut self, l: T, r: U) {
+        let change = Change::Insert(self.left(l), self.right(r));
+        self.changes.push(change);
+    }
+    /// Stages removing the pair holding a left value.
+    pub fn remove(&mut self, l: T) {
+        let change = Change::Remove(self.left(l));
+        self.changes.push(change);
+    }
+    /// Stages removing the pair holding a right value.
+    pub fn remove_value(&mut self, r: U) {
+        let change = Change::RemoveValue(self.right(r));
+        self.changes.push(change);
+    }
+    /// Stages binding a left value to `r`, which inserts the pair if `l` is not bound by then.
+    pub fn update_key(&mut self, l: T, r: U) {
+        let change = Change::UpdateKey(self.left(l), self.right(r));
+        self.changes.push(change);
+    }
+    /// Stages binding a right value to `l`, which inserts the pair if `r` is not bound by then.
+    pub fn update_value(&mut self, r: U, l: T) {
+        let change = Change::UpdateValue(self.right(r), self.left(l));
+        self.changes.push(change);
+    }
+    /// The number of staged changes.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+    fn left(&mut self, l: T) -> usize {
+        self.lefts.push(l);
+        self.lefts.len() - 1
+    }
+    fn right(&mut self, r: U) -> usize {
+        self.rights.push(r);
+        self.rights.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BiBTreeMap, BiMap, Conflict, Side};
+
+    #[test]
+    fn swap_and_rename() {
+        let mut map: BiBTreeMap<char, u32> =
+            vec![('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        let staged = map.transaction(|tx| {
+            tx.update_key('a', 2);
+            tx.update_key('b', 1);
+            tx.update_value(3, 'd');
+            tx.len()
+        });
+
+        assert_eq!(staged, Ok(3));
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(&'a', &2), (&'b', &1), (&'d', &3)]
+        );
+    }
+
+    #[test]
+    fn staged_in_order() {
+        let mut map: BiBTreeMap<char, u32> =
+            vec![('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        let result = map.transaction(|tx| {
+            tx.insert('e', 5);
+            tx.remove('a');
+            tx.insert('a', 3);
+            tx.remove_value(3);
+            tx.update_key('f', 6);
+            tx.insert('a', 1);
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(&'a', &1), (&'b', &2), (&'e', &5), (&'f', &6)]
+        );
+    }
+
+    #[test]
+    fn rollback() {
+        let mut map: BiBTreeMap<char, u32> =
+            vec![('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        let result = map.transaction(|tx| {
+            tx.update_key('a', 2);
+            tx.insert('d', 3);
+            tx.insert('e', 9);
+        });
+
+        assert_eq!(
+            result,
+            Err(vec![
+                Conflict { pair: ('a', 2), side: Side::Right },
+                Conflict { pair: ('d', 3), side: Side::Right },
+            ])
+        );
+        let unchanged: BiBTreeMap<char, u32> =
+            vec![('a', 1), ('b', 2), ('c', 3)].into_iter().collect();
+        assert_eq!(map, unchanged);
+    }
+
+    #[test]
+    fn rotate_many() {
+        let mut map: BiMap<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+        let result = map.transaction(|tx| {
+            for i in 0..10_000 {
+                tx.update_key(i, (i + 1) % 10_000);
+            }
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(map.len(), 10_000);
+        assert!((0..10_000).all(|i| map.get_key(&i) == Some(&((i + 1) % 10_000))));
+    }
+}
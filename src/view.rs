@@ -0,0 +1,351 @@
+use std::fmt;
+
+use super::{
+    get, insert_overwrite, modify, remove, retain, swap, try_insert, update, BiMap, Entry, Hashed,
+    InsertError, Iter, LeftValues, Lookup, Overwritten, RightValues, Storage, Store, UpdateError,
+};
+
+impl<T: Eq, U: Eq, LS: Storage<T, U>, RS: Storage<U, T>> BiMap<T, U, LS, RS> {
+    /// The map as a `View`, to pass to code that also takes reversed views.
+    pub fn view(&self) -> View<'_, T, U, LS, RS> {
+        View {
+            map1: &self.left_to_right,
+            map2: &self.right_to_left,
+        }
+    }
+    pub fn view_mut(&mut self) -> ViewMut<'_, T, U, LS, RS> {
+        ViewMut {
+            map1: &mut self.left_to_right,
+            map2: &mut self.right_to_left,
+        }
+    }
+    /// The map with its sides swapped: the left values of the view are the right values of the
+    /// map.
+    pub fn rev(&self) -> View<'_, U, T, RS, LS> {
+        View {
+            map1: &self.right_to_left,
+            map2: &self.left_to_right,
+        }
+    }
+    pub fn rev_mut(&mut self) -> ViewMut<'_, U, T, RS, LS> {
+        ViewMut {
+            map1: &mut self.right_to_left,
+            map2: &mut self.left_to_right,
+        }
+    }
+}
+
+/// A borrowed `BiMap`, possibly with its sides swapped, created by `BiMap::view` or `BiMap::rev`.
+///
+/// `K` are the left values of the view and `V` the right values, with `KS` and `VS` the storages
+/// of the two directions. Reversing a view of a `BiMap<T, U, LS, RS>` gives a
+/// `View<U, T, RS, LS>`, so code written against one side works on either.
+pub struct View<'a, K: 'a, V: 'a, KS: Storage<K, V> + 'a = Hashed, VS = KS>
+where
+    VS: Storage<V, K> + 'a,
+{
+    map1: &'a KS::Map,
+    map2: &'a VS::Map,
+}
+
+impl<'a, K: Eq, V: Eq, KS: Storage<K, V>, VS: Storage<V, K>> View<'a, K, V, KS, VS> {
+    pub fn get_key<Q: ?Sized>(&self, l: &Q) -> Option<&'a V>
+    where
+        KS::Map: Lookup<K, V, Q>,
+    {
+        get(self.map1, l)
+    }
+    pub fn get_value<Q: ?Sized>(&self, r: &Q) -> Option<&'a K>
+    where
+        VS::Map: Lookup<V, K, Q>,
+    {
+        get(self.map2, r)
+    }
+    pub fn contains_left<Q: ?Sized>(&self, l: &Q) -> bool
+    where
+        KS::Map: Lookup<K, V, Q>,
+    {
+        self.map1.contains_key(l)
+    }
+    pub fn contains_right<Q: ?Sized>(&self, r: &Q) -> bool
+    where
+        VS::Map: Lookup<V, K, Q>,
+    {
+        self.map2.contains_key(r)
+    }
+    pub fn len(&self) -> usize {
+        self.map1.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map1.is_empty()
+    }
+    pub fn iter(&self) -> Iter<'a, K, V, KS> {
+        Iter {
+            inner: self.map1.iter(),
+        }
+    }
+    pub fn left_values(&self) -> LeftValues<'a, K, V, KS> {
+        LeftValues {
+            inner: self.map1.keys(),
+        }
+    }
+    pub fn right_values(&self) -> RightValues<'a, K, V, VS> {
+        RightValues {
+            inner: self.map2.keys(),
+        }
+    }
+    /// The view with its sides swapped back.
+    pub fn rev(&self) -> View<'a, V, K, VS, KS> {
+        View {
+            map1: self.map2,
+            map2: self.map1,
+        }
+    }
+}
+
+impl<'a, K, V, KS: Storage<K, V>, VS: Storage<V, K>> Clone for View<'a, K, V, KS, VS> {
+    fn clone(&self) -> View<'a, K, V, KS, VS> {
+        *self
+    }
+}
+
+impl<'a, K, V, KS: Storage<K, V>, VS: Storage<V, K>> Copy for View<'a, K, V, KS, VS> {}
+
+impl<'a, K, V, KS, VS> fmt::Debug for View<'a, K, V, KS, VS>
+where
+    K: Eq + fmt::Debug,
+    V: Eq + fmt::Debug,
+    KS: Storage<K, V>,
+    VS: Storage<V, K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// A mutably borrowed `BiMap`, possibly with its sides swapped, created by `BiMap::view_mut` or
+/// `BiMap::rev_mut`.
+///
+/// It offers the methods of `BiMap` that do not take the map by value, with `K` as the left side;
+/// conflicts are reported relative to the view.
+pub struct ViewMut<'a, K: 'a, V: 'a, KS: Storage<K, V> + 'a = Hashed, VS = KS>
+where
+    VS: Storage<V, K> + 'a,
+{
+    map1: &'a mut KS::Map,
+    map2: &'a mut VS::Map,
+}
+
+impl<'a, K: Eq, V: Eq, KS: Storage<K, V>, VS: Storage<V, K>> ViewMut<'a, K, V, KS, VS> {
+    /// A shared view of the same map, borrowed from this one.
+    pub fn as_view(&self) -> View<'_, K, V, KS, VS> {
+        View {
+            map1: self.map1,
+            map2: self.map2,
+        }
+    }
+    pub fn get_key<Q: ?Sized>(&self, l: &Q) -> Option<&V>
+    where
+        KS::Map: Lookup<K, V, Q>,
+    {
+        get(&*self.map1, l)
+    }
+    pub fn get_value<Q: ?Sized>(&self, r: &Q) -> Option<&K>
+    where
+        VS::Map: Lookup<V, K, Q>,
+    {
+        get(&*self.map2, r)
+    }
+    pub fn contains_left<Q: ?Sized>(&self, l: &Q) -> bool
+    where
+        KS::Map: Lookup<K, V, Q>,
+    {
+        self.map1.contains_key(l)
+    }
+    pub fn contains_right<Q: ?Sized>(&self, r: &Q) -> bool
+    where
+        VS::Map: Lookup<V, K, Q>,
+    {
+        self.map2.contains_key(r)
+    }
+    pub fn len(&self) -> usize {
+        self.map1.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map1.is_empty()
+    }
+    pub fn iter(&self) -> Iter<'_, K, V, KS> {
+        self.as_view().iter()
+    }
+    pub fn insert_key(&mut self, l: K, r: V) {
+        if let Err(error) = self.try_insert(l, r) {
+            panic!("{}", error);
+        }
+    }
+    pub fn insert_value(&mut self, r: V, l: K) {
+        self.insert_key(l, r);
+    }
+    pub fn try_insert(&mut self, l: K, r: V) -> Result<(), InsertError<'_, K, V>> {
+        try_insert(&mut *self.map1, &mut *self.map2, l, r)
+    }
+    pub fn try_insert_value(&mut self, r: V, l: K) -> Result<(), InsertError<'_, K, V>> {
+        self.try_insert(l, r)
+    }
+    pub fn insert_overwrite(&mut self, l: K, r: V) -> Overwritten<K, V> {
+        insert_overwrite(&mut *self.map1, &mut *self.map2, l, r)
+    }
+    pub fn update_key(&mut self, l: K, r: V) -> Option<V> {
+        match self.try_update_key(l, r) {
+            Ok(old) => old,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    pub fn update_value(&mut self, r: V, l: K) -> Option<K> {
+        match self.try_update_value(r, l) {
+            Ok(old) => old,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    pub fn try_update_key(&mut self, l: K, r: V) -> Result<Option<V>, UpdateError<'_, V, K>> {
+        update(&mut *self.map1, &mut *self.map2, l, r)
+    }
+    pub fn try_update_value(&mut self, r: V, l: K) -> Result<Option<K>, UpdateError<'_, K, V>> {
+        update(&mut *self.map2, &mut *self.map1, r, l)
+    }
+    pub fn modify_right<Q, F>(&mut self, l: &Q, f: F) -> Result<bool, UpdateError<'_, V, K>>
+    where
+        V: Clone,
+        Q: ?Sized,
+        F: FnOnce(&mut V),
+        KS::Map: Lookup<K, V, Q>,
+    {
+        modify(&mut *self.map1, &mut *self.map2, l, f)
+    }
+    pub fn modify_left<Q, F>(&mut self, r: &Q, f: F) -> Result<bool, UpdateError<'_, K, V>>
+    where
+        K: Clone,
+        Q: ?Sized,
+        F: FnOnce(&mut K),
+        VS::Map: Lookup<V, K, Q>,
+    {
+        modify(&mut *self.map2, &mut *self.map1, r, f)
+    }
+    pub fn swap_right<Q: ?Sized>(&mut self, l1: &Q, l2: &Q) -> bool
+    where
+        KS::Map: Lookup<K, V, Q>,
+    {
+        swap(&mut *self.map1, &mut *self.map2, l1, l2)
+    }
+    pub fn swap_left<Q: ?Sized>(&mut self, r1: &Q, r2: &Q) -> bool
+    where
+        VS::Map: Lookup<V, K, Q>,
+    {
+        swap(&mut *self.map2, &mut *self.map1, r1, r2)
+    }
+    pub fn remove<Q: ?Sized>(&mut self, l: &Q) -> Option<V>
+    where
+        KS::Map: Lookup<K, V, Q>,
+    {
+        remove(&mut *self.map1, &mut *self.map2, l)
+    }
+    pub fn remove_value<Q: ?Sized>(&mut self, r: &Q) -> Option<K>
+    where
+        VS::Map: Lookup<V, K, Q>,
+    {
+        remove(&mut *self.map2, &mut *self.map1, r)
+    }
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, f: F) {
+        retain(&mut *self.map1, &mut *self.map2, f);
+    }
+    pub fn entry_left(&mut self, l: K) -> Entry<'_, K, V, KS, VS> {
+        Entry::new(&mut *self.map1, &mut *self.map2, l)
+    }
+    pub fn entry_right(&mut self, r: V) -> Entry<'_, V, K, VS, KS> {
+        Entry::new(&mut *self.map2, &mut *self.map1, r)
+    }
+    /// The view with its sides swapped back.
+    pub fn rev(self) -> ViewMut<'a, V, K, VS, KS> {
+        ViewMut {
+            map1: self.map2,
+            map2: self.map1,
+        }
+    }
+}
+
+impl<'a, K, V, KS, VS> fmt::Debug for ViewMut<'a, K, V, KS, VS>
+where
+    K: Eq + fmt::Debug,
+    V: Eq + fmt::Debug,
+    KS: Storage<K, V>,
+    VS: Storage<V, K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.as_view(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BiMap, Hashed, InsertError, Ordered, Storage};
+    use super::ViewMut;
+
+    /// Binds each left value to the next one, written once for both sides.
+    fn chain<K, KS, VS>(mut view: ViewMut<K, K, KS, VS>, values: &[K])
+    where
+        K: Clone + Eq,
+        KS: Storage<K, K>,
+        VS: Storage<K, K>,
+    {
+        for pair in values.windows(2) {
+            view.insert_key(pair[0].clone(), pair[1].clone());
+        }
+    }
+
+    #[test]
+    fn rev() {
+        let map: BiMap<&str, u32> = vec![("abc", 1), ("def", 2)].into_iter().collect();
+        let rev = map.rev();
+
+        assert_eq!(rev.get_key(&1), Some(&"abc"));
+        assert_eq!(rev.get_value(&"def"), Some(&2));
+        assert!(rev.contains_left(&2));
+        assert!(!rev.contains_right(&"ghi"));
+        assert_eq!(rev.len(), 2);
+        let mut pairs: Vec<_> = rev.iter().collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(&1, &"abc"), (&2, &"def")]);
+        assert_eq!(rev.rev().get_key(&"abc"), Some(&1));
+        assert_eq!(format!("{:?}", map.view()), format!("{:?}", map));
+    }
+
+    #[test]
+    fn rev_mut() {
+        let mut map: BiMap<&str, u32> = vec![("abc", 1), ("def", 2)].into_iter().collect();
+        let mut rev = map.rev_mut();
+
+        rev.insert_key(3, "ghi");
+        assert_eq!(
+            rev.try_insert(3, "jkl"),
+            Err(InsertError::Left { pair: (3, "jkl"), partner: &"ghi" })
+        );
+        assert_eq!(rev.update_key(1, "xyz"), Some("abc"));
+        assert_eq!(rev.remove_value(&"def"), Some(2));
+        assert_eq!(rev.modify_left(&"ghi", |l| *l += 10), Ok(true));
+        assert!(rev.swap_right(&1, &13));
+        assert_eq!(map.get_key(&"ghi"), Some(&1));
+        assert_eq!(map.get_value(&13), Some(&"xyz"));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn either_side() {
+        let mut map: BiMap<u32, u32, Hashed, Ordered> = BiMap::default();
+        chain(map.view_mut(), &[1, 2, 3]);
+        chain(map.rev_mut(), &[10, 20]);
+
+        assert_eq!(map.get_key(&1), Some(&2));
+        assert_eq!(map.get_key(&2), Some(&3));
+        assert_eq!(map.get_value(&10), Some(&20));
+        assert_eq!(map.right_values().cloned().collect::<Vec<_>>(), vec![2, 3, 10]);
+    }
+}